@@ -1,16 +1,17 @@
 
 extern crate serde_json;
 extern crate termcolor;
+extern crate time;
 extern crate hyper;
 
 pub use hyper::client::Client;
-use std::io::Read;
-use std;
-use std::process;
+use std::cmp;
 use std::env;
 use std::io::Write;
 use self::termcolor::{Color, ColorChoice, ColorSpec, Stdout, WriteColor};
 
+use weather::{fetch, Condition, Observation, Place, Query, WeatherError, WeatherProvider};
+
 static APIXU_URL: &'static str = "https://api.apixu.com/v1/";
 static WEATHER_CONDITIONS: [(&'static str, u32); 48] =
     [("Sunny", 1000),
@@ -66,8 +67,6 @@ struct ApixuCfg {
     api_key: String,
 }
 
-type City = String;
-
 #[derive(Deserialize, Debug, Default)]
 pub struct Location {
     /// Latitude in decimal degree
@@ -88,6 +87,22 @@ pub struct Location {
     localtime: String,
 }
 
+impl Location {
+    fn to_place(&self) -> Place {
+        // The local time is given to the minute: the offset is a whole
+        // number of quarters of an hour anyway.
+        let utc_offset = time::strptime(&self.localtime, "%Y-%m-%d %H:%M").ok().map(|tm| {
+            let seconds = tm.to_timespec().sec - self.localtime_epoch as i64;
+            (seconds as f64 / 900.0).round() as i64 * 900
+        });
+        Place {
+            name: self.name.clone(),
+            country: self.country.clone(),
+            utc_offset_s: utc_offset,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug, Default)]
 pub struct CurrentWeather {
     pub location: Location,
@@ -138,42 +153,64 @@ pub struct Current {
     /// Feels like temperature as fahrenheit
     feelslike_f: f32,
     pub is_day: u8, // 1 = Yes 0 = No
+    /// UV index
+    pub uv: Option<f32>,
 }
 
-#[derive(Debug)]
-pub enum ApixuError {
-    FailedToContactRemoteHost(hyper::error::Error),
-    InvalidRequest(String, hyper::client::Response),
-    IOError(std::io::Error),
-    ParseJsonError(serde_json::error::Error),
-}
-
-impl From<hyper::error::Error> for ApixuError {
-    fn from(err: hyper::error::Error) -> ApixuError {
-        ApixuError::FailedToContactRemoteHost(err)
-    }
+#[derive(Deserialize, Debug, Default)]
+pub struct ForecastWeather {
+    pub location: Location,
+    pub forecast: Forecast,
 }
 
-impl From<serde_json::error::Error> for ApixuError {
-    fn from(err: serde_json::error::Error) -> ApixuError {
-        ApixuError::ParseJsonError(err)
-    }
+#[derive(Deserialize, Debug, Default)]
+pub struct Forecast {
+    pub forecastday: Vec<ForecastDay>,
 }
 
-impl From<std::io::Error> for ApixuError {
-    fn from(err: std::io::Error) -> ApixuError {
-        ApixuError::IOError(err)
-    }
+#[derive(Deserialize, Debug, Default)]
+pub struct ForecastDay {
+    /// Forecast date, e.g.: 2016-12-15
+    pub date: String,
+    pub hour: Vec<Hour>,
 }
 
-lazy_static! {
-    static ref APIXU_CFG: ApixuCfg = {
-        ApixuCfg { api_key: get_apixu_key() }
-    };
+#[derive(Deserialize, Debug, Default)]
+pub struct Hour {
+    /// Local date and time of the start of the hour, e.g.: 2016-12-15 09:00
+    pub time: String,
+    /// Temperature in celsius
+    pub temp_c: f32,
+    pub is_day: u8, // 1 = Yes 0 = No
+    pub condition: WeatherCondition,
+    /// Wind speed in kilometer per hour
+    pub wind_kph: f32,
+    /// Wind gusts in kilometer per hour
+    pub gust_kph: Option<f32>,
+    /// Pressure in millibars
+    pub pressure_mb: f32,
+    /// Precipitation amount in millimeters
+    precip_mm: f32,
+    /// Humidity as percentage
+    pub humidity: u8,
+    /// Cloud cover as percentage
+    pub cloud: u8,
+    /// Feels like temperature as celcius
+    pub feelslike_c: f32,
+    /// Dew point in celsius
+    pub dewpoint_c: Option<f32>,
+    /// Visibility in kilometers
+    pub vis_km: Option<f32>,
+    /// Chance of rain as percentage
+    pub chance_of_rain: Option<u8>,
+    /// Chance of snow as percentage
+    pub chance_of_snow: Option<u8>,
+    /// UV index
+    pub uv: Option<f32>,
 }
 
 // TODO: Investigate how/if termcolor supports stderr.
-fn get_apixu_key() -> String {
+fn get_apixu_key() -> Result<String, WeatherError> {
     match env::var("APIXU_API_KEY") {
         Err(_) => {
             let mut stdout = Stdout::new(ColorChoice::Always);
@@ -184,63 +221,173 @@ You need to the env var APIXU_API_KEY to be set. (Try 'export APIXU_API_KEY=xxxx
 Please go to "https://www.apixu.com/" and create a new free account in
 order to get a valid API key for the weather service.
 
+Alternatively, try a provider which doesn't need a key (e.g. '--provider open-meteo').
 Visit the "Prerequisites" section of the README for more information.
 "###);
-            process::exit(1);
+            let _ = stdout.reset();
+            Err(WeatherError::MissingApiKey("APIXU_API_KEY"))
         }
-        Ok(v) => v,
+        Ok(v) => Ok(v),
     }
 }
 
 // Build an Url to be used by Hyper.
-fn mk_url(uri_path: &str, params: Vec<(&str, &String)>) -> String {
+fn mk_url(cfg: &ApixuCfg, uri_path: &str, params: Vec<(&str, &String)>) -> String {
     let param_string: String = params.iter()
         .fold(String::new(), |acc, &x| format!("{}&{}={}", acc, x.0, x.1));
     String::from(format!("{}{}?key={}{}",
                          APIXU_URL,
                          uri_path,
-                         APIXU_CFG.api_key,
+                         cfg.api_key,
                          param_string))
 }
 
+/// The Apixu weather API, authenticated via the APIXU_API_KEY env var.
+pub struct Apixu {
+    cfg: ApixuCfg,
+}
+
+impl Apixu {
+    pub fn from_env() -> Result<Apixu, WeatherError> {
+        get_apixu_key().map(|key| Apixu { cfg: ApixuCfg { api_key: key } })
+    }
+}
+
+impl WeatherProvider for Apixu {
+    fn name(&self) -> &'static str {
+        "apixu"
+    }
+
+    fn current_weather(&self, client: &Client, query: &Query) -> Result<Observation, WeatherError> {
+        current_weather(client, &self.cfg, query).map(|cw| to_observation(&cw))
+    }
+
+    fn hourly_forecast(&self,
+                       client: &Client,
+                       query: &Query)
+                       -> Result<Vec<Observation>, WeatherError> {
+        forecast_weather(client, &self.cfg, query).map(|fw| to_forecast(&fw))
+    }
+}
+
+fn to_query_param(query: &Query) -> String {
+    match *query {
+        Query::AutoIp => "auto:ip".to_owned(),
+        Query::City(ref c) => c.clone(),
+        Query::Coords(lat, lon) => format!("{},{}", lat, lon),
+    }
+}
+
 /// Gets the current weather based on Auto IP.
 // TODO: Better error handling.
-pub fn current_weather(client: &hyper::client::Client,
-                       city: &Option<City>)
-                       -> Result<CurrentWeather, ApixuError> {
-    let the_city = city.clone().unwrap_or("auto:ip".to_owned());
-    let url = mk_url("current.json", vec![("q", &the_city)]);
-    let mut response = try!(client.get(&url).send());
-    if response.status != hyper::status::StatusCode::Ok {
-        return Err(ApixuError::InvalidRequest(url, response));
-    }
-    let mut body = String::new();
-    let _ = try!(response.read_to_string(&mut body));
+fn current_weather(client: &hyper::client::Client,
+                   cfg: &ApixuCfg,
+                   query: &Query)
+                   -> Result<CurrentWeather, WeatherError> {
+    let the_city = to_query_param(query);
+    let url = mk_url(cfg, "current.json", vec![("q", &the_city)]);
+    let body = try!(fetch(client, &url));
     let cw: CurrentWeather = try!(serde_json::from_str(&body));
     Ok(cw)
 }
 
-pub fn parse_hours_from_last_updated<'a>(last_updated: &'a str) -> Option<u8> {
-    match *last_updated.to_owned().split_whitespace().collect::<Vec<_>>().as_slice() {
-        [_, time] => {
-            match *time.split(":").collect::<Vec<_>>().as_slice() {
-                [h, _] => return str::parse(h).ok(),
-                _ => return None,
+/// Gets the hourly forecast for today and the next two days.
+fn forecast_weather(client: &hyper::client::Client,
+                    cfg: &ApixuCfg,
+                    query: &Query)
+                    -> Result<ForecastWeather, WeatherError> {
+    let the_city = to_query_param(query);
+    let days = "3".to_owned();
+    let url = mk_url(cfg, "forecast.json", vec![("q", &the_city), ("days", &days)]);
+    let body = try!(fetch(client, &url));
+    let fw: ForecastWeather = try!(serde_json::from_str(&body));
+    Ok(fw)
+}
+
+pub fn to_observation(cw: &CurrentWeather) -> Observation {
+    Observation {
+        place: cw.location.to_place(),
+        local_time: cw.current.last_updated.clone(),
+        temp_c: cw.current.temp_c,
+        feelslike_c: cw.current.feelslike_c,
+        wind_kph: cw.current.wind_kph,
+        gust_kph: None,
+        visibility_km: None,
+        dew_point_c: None,
+        humidity: Some(cw.current.humidity),
+        cloud: Some(cw.current.cloud),
+        precip_mm: Some(cw.current.precip_mm),
+        precip_chance: None,
+        uv_index: cw.current.uv,
+        elevation_m: None,
+        is_day: cw.current.is_day != 0,
+        condition: to_condition(cw.current.condition.code),
+        condition_text: cw.current.condition.text.clone(),
+    }
+}
+
+pub fn to_forecast(fw: &ForecastWeather) -> Vec<Observation> {
+    let place = fw.location.to_place();
+    fw.forecast
+        .forecastday
+        .iter()
+        .flat_map(|day| day.hour.iter())
+        .map(|hour| {
+            Observation {
+                place: place.clone(),
+                local_time: hour.time.clone(),
+                temp_c: hour.temp_c,
+                feelslike_c: hour.feelslike_c,
+                wind_kph: hour.wind_kph,
+                gust_kph: hour.gust_kph,
+                visibility_km: hour.vis_km,
+                dew_point_c: hour.dewpoint_c,
+                humidity: Some(hour.humidity),
+                cloud: Some(hour.cloud),
+                precip_mm: Some(hour.precip_mm),
+                precip_chance: match (hour.chance_of_rain, hour.chance_of_snow) {
+                    (Some(r), Some(s)) => Some(cmp::max(r, s)),
+                    (r, s) => r.or(s),
+                },
+                uv_index: hour.uv,
+                elevation_m: None,
+                is_day: hour.is_day != 0,
+                condition: to_condition(hour.condition.code),
+                condition_text: hour.condition.text.clone(),
             }
-        }
-        _ => return None,
+        })
+        .collect()
+}
+
+// See WEATHER_CONDITIONS for the meaning of each code.
+pub fn to_condition(code: u32) -> Condition {
+    match code {
+        1000 => Condition::Clear,
+        1003 => Condition::PartlyCloudy,
+        1006 | 1009 => Condition::Overcast,
+        1030 | 1135 | 1147 => Condition::Fog,
+        1072 | 1150 | 1153 | 1168 | 1171 => Condition::Drizzle,
+        1063 | 1180 | 1183 | 1240 => Condition::LightRain,
+        1186 | 1189 | 1243 => Condition::Rain,
+        1192 | 1195 | 1246 => Condition::HeavyRain,
+        1087 | 1273 | 1276 => Condition::Thunderstorm,
+        1069 | 1198 | 1201 | 1204 | 1207 | 1237 | 1249 | 1252 | 1261 | 1264 => Condition::Sleet,
+        // Assume snow otherwise
+        _ => Condition::Snow,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::serde_json;
 
     #[test]
     #[ignore]
     fn can_decode_a_current_weather_request() {
         let client = Client::new();
-        match current_weather(&client, &Some("Marsala".to_owned())) {
+        let cfg = ApixuCfg { api_key: get_apixu_key().unwrap() };
+        match current_weather(&client, &cfg, &Query::City("Marsala".to_owned())) {
             Ok(cw) => {
                 assert_eq!(cw.location.name, "Marsala");
                 assert_eq!(cw.location.country, "Italy")
@@ -250,10 +397,27 @@ mod tests {
     }
 
     #[test]
-    fn can_parse_last_updated_field_into_hours() {
-        let test1 = "2016-12-15 09:22";
-        let test2 = "2016-12-15 3:24";
-        assert_eq!(parse_hours_from_last_updated(&test1), Some(9));
-        assert_eq!(parse_hours_from_last_updated(&test2), Some(3));
+    fn can_decode_the_hourly_forecast() {
+        let fw: ForecastWeather =
+            serde_json::from_str(include_str!("../fixtures/apixu/forecast.json")).unwrap();
+        let forecast = to_forecast(&fw);
+        assert_eq!(forecast.len(), 3);
+        assert_eq!(forecast[0].place.name, "Marsala");
+        assert_eq!(forecast[0].place.utc_offset_s, Some(7200));
+        assert_eq!(forecast[1].local_time, "2026-10-19 06:00");
+        assert_eq!(forecast[1].temp_c, 11.4);
+        assert_eq!(forecast[1].gust_kph, Some(24.1));
+        assert_eq!(forecast[1].condition, Condition::LightRain);
+        assert!(!forecast[1].is_day);
+        assert_eq!(forecast[2].uv_index, Some(1.0));
+    }
+
+    #[test]
+    fn can_map_condition_codes() {
+        assert_eq!(to_condition(1000), Condition::Clear);
+        assert_eq!(to_condition(1003), Condition::PartlyCloudy);
+        assert_eq!(to_condition(1183), Condition::LightRain);
+        assert_eq!(to_condition(1195), Condition::HeavyRain);
+        assert_eq!(to_condition(1225), Condition::Snow);
     }
 }
@@ -0,0 +1,30 @@
+extern crate serde_json;
+
+use std;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+/// Reads a value previously stored with `write_json`. Any failure (missing
+/// file, stale format, ...) is treated as a cache miss.
+pub fn read_json<T: ::serde::Deserialize>(path: &Path) -> Option<T> {
+    let mut contents = String::new();
+    let mut file = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return None,
+    };
+    if file.read_to_string(&mut contents).is_err() {
+        return None;
+    }
+    serde_json::from_str(&contents).ok()
+}
+
+pub fn write_json<T: ::serde::Serialize>(path: &Path, value: &T) -> Result<(), std::io::Error> {
+    if let Some(dir) = path.parent() {
+        try!(fs::create_dir_all(dir));
+    }
+    let json = try!(serde_json::to_string(value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}", e))));
+    let mut file = try!(File::create(path));
+    file.write_all(json.as_bytes())
+}
@@ -1,14 +1,76 @@
 
 extern crate clap;
+extern crate time;
 
-use cli::clap::{Arg, App};
+use cli::clap::{Arg, ArgGroup, App, AppSettings, SubCommand};
 use std::fmt;
 use std;
 
+use config::Settings;
+use feedback::Comfort;
+use recommendation::BodyPart;
+use weather;
+
 #[derive(Debug)]
 pub enum CliParseError {
     ParseGenderError(std::string::String),
     ParseIntensityError(std::string::String),
+    ParseCoordsError(std::string::String),
+    /// One of the weather override flags has an invalid value.
+    ParseOverrideError(std::string::String),
+    /// The planned start of the run (`--at` or `--in`) is invalid.
+    ParseStartError(std::string::String),
+    ParseDurationError(std::string::String),
+    ParseThermalOffsetError(std::string::String),
+    ParseElevationError(std::string::String),
+    ParseFeedbackError(std::string::String),
+}
+
+/// What the user asked viktor to do.
+#[derive(Debug)]
+pub enum Command {
+    /// Tell what to wear
+    Recommend(Args),
+    /// Record how the last run felt
+    Feedback(FeedbackArgs),
+}
+
+impl Command {
+    pub fn parse() -> Result<Command, CliParseError> {
+        let matches = cli().get_matches();
+        match matches.subcommand_matches("feedback") {
+            Some(m) => FeedbackArgs::from_matches(m).map(Command::Feedback),
+            None => Args::from_matches(&matches).map(Command::Recommend),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct FeedbackArgs {
+    pub comfort: Comfort,
+    /// The body part it is about, if not the whole body.
+    pub body_part: Option<BodyPart>,
+}
+
+impl FeedbackArgs {
+    fn from_matches(matches: &clap::ArgMatches) -> Result<FeedbackArgs, CliParseError> {
+        let comfort = try!(["too-cold", "just-right", "too-warm"]
+            .iter()
+            .find(|c| matches.is_present(c))
+            .and_then(|c| Comfort::parse(c))
+            .ok_or(CliParseError::ParseFeedbackError(String::from("How did it feel?"))));
+        let body_part = match matches.value_of("body-part") {
+            None => None,
+            Some(b) => {
+                Some(try!(BodyPart::parse(b)
+                    .ok_or(CliParseError::ParseFeedbackError(String::from(b)))))
+            }
+        };
+        Ok(FeedbackArgs {
+            comfort: comfort,
+            body_part: body_part,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -16,26 +78,156 @@ pub struct Args {
     pub gender: Gender,
     pub intensity: Intensity,
     pub city: Option<String>,
+    pub coords: Option<(f32, f32)>,
+    pub provider: Option<String>,
+    pub profile: Option<String>,
+    pub metar: Option<String>,
+    pub metar_file: Option<String>,
+    pub weather_file: Option<String>,
+    pub overrides: weather::Overrides,
+    /// When the run starts, if not now.
+    pub start: Option<Start>,
+    /// How long the run lasts, in minutes.
+    pub duration: Option<i64>,
+    /// "local" or "runners-world"
+    pub engine: Option<String>,
+    /// The clothing rules file of the local engine, if not the bundled one.
+    pub rules: Option<String>,
+    /// How much warmer (or colder, if negative) than others you feel when
+    /// running, in celsius.
+    pub thermal_offset: Option<f32>,
+    /// How high you run, in meters, if not where the weather is observed.
+    pub elevation: Option<f32>,
+    /// How high the weather station is, in meters, for the providers which
+    /// don't tell it and for the weather described by hand.
+    pub station_elevation: Option<f32>,
+    /// A GPX file of the route, to dress for its highest point.
+    pub route: Option<String>,
+    /// Where to save the Runner's World page, if it can't be scraped.
+    pub dump_html: Option<String>,
+    /// Whether to tell how each answer of the form was chosen.
+    pub explain: bool,
 }
 
 // TODO: This is horrid, we should be able to use lifetime specifier
 // to avoid the conversion to String.
 impl Args {
-    pub fn parse() -> Result<Args, CliParseError> {
-        let matches = cli().get_matches();
+    fn from_matches(matches: &clap::ArgMatches) -> Result<Args, CliParseError> {
         let gender = try!(matches.value_of("gender")
             .ok_or(CliParseError::ParseGenderError(String::from("Gender is required.")))
             .and_then(parse_gender));
         let intensity = try!(matches.value_of("intensity")
             .ok_or(CliParseError::ParseIntensityError(String::from("Intensity is required.")))
             .and_then(parse_intensity));
+        let coords = match matches.value_of("coords") {
+            None => None,
+            Some(c) => Some(try!(parse_coords(c))),
+        };
+        let overrides = try!(parse_overrides(matches));
+        let start = match (matches.value_of("at"), matches.value_of("in")) {
+            (Some(at), _) => Some(try!(parse_start(at))),
+            (None, Some(delay)) => Some(try!(parse_delay(delay))),
+            (None, None) => None,
+        };
+        let duration = match matches.value_of("duration") {
+            None => None,
+            Some(d) => {
+                Some(try!(parse_minutes(d).ok_or(CliParseError::ParseDurationError(String::from(d)))))
+            }
+        };
+        let thermal_offset = match matches.value_of("thermal-offset") {
+            None => None,
+            Some(o) => {
+                Some(try!(parse_temperature_difference(o)
+                    .ok_or(CliParseError::ParseThermalOffsetError(String::from(o)))))
+            }
+        };
+        let elevation = match matches.value_of("elevation") {
+            None => None,
+            Some(e) => {
+                Some(try!(parse_elevation(e).ok_or(CliParseError::ParseElevationError(String::from(e)))))
+            }
+        };
+        let station_elevation = match matches.value_of("station-elevation") {
+            None => None,
+            Some(e) => {
+                Some(try!(parse_elevation(e).ok_or(CliParseError::ParseElevationError(String::from(e)))))
+            }
+        };
         let args = Args {
             gender: gender,
             intensity: intensity,
             city: matches.value_of("city").map(String::from),
+            coords: coords,
+            provider: matches.value_of("provider").map(String::from),
+            profile: matches.value_of("profile").map(String::from),
+            metar: matches.value_of("metar").map(String::from),
+            metar_file: matches.value_of("metar-file").map(String::from),
+            weather_file: matches.value_of("weather-file").map(String::from),
+            overrides: overrides,
+            start: start,
+            duration: duration,
+            engine: matches.value_of("engine").map(String::from),
+            rules: matches.value_of("rules").map(String::from),
+            thermal_offset: thermal_offset,
+            elevation: elevation,
+            station_elevation: station_elevation,
+            route: matches.value_of("route").map(String::from),
+            dump_html: matches.value_of("dump-html").map(String::from),
+            explain: matches.is_present("explain"),
         };
         Ok(args)
     }
+
+    /// Whether the user asked for the weather somewhere in particular (here
+    /// or in the config file), rather than describing it with the override
+    /// flags alone.
+    pub fn has_weather_source(&self, settings: &Settings) -> bool {
+        self.city.is_some() || self.coords.is_some() || self.provider.is_some() ||
+        settings.provider.is_some() || self.metar.is_some() || self.metar_file.is_some() ||
+        self.weather_file.is_some()
+    }
+}
+
+/// When the run starts, in the local time of the place it's in. As the
+/// machine's clock may be in another time zone, it can only be worked out
+/// once the weather provider tells the time there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Start {
+    /// On the given day and time, as `YYYY-MM-DD HH:MM`
+    At(String),
+    /// The next time the clock reads `HH:MM`: today, or tomorrow if it's
+    /// already past, as when planning the night before
+    Next(String),
+    /// In so many minutes
+    In(i64),
+}
+
+impl Start {
+    /// The start as `YYYY-MM-DD HH:MM`, given the local time now.
+    pub fn resolve(&self, now: &str) -> String {
+        match *self {
+            Start::At(ref at) => at.clone(),
+            Start::Next(ref at) => {
+                let today = format!("{} {}", now.split_whitespace().next().unwrap_or(""), at);
+                if today.as_str() >= now {
+                    today
+                } else {
+                    Start::In(24 * 60).resolve(&today)
+                }
+            }
+            Start::In(minutes) => {
+                match time::strptime(now, "%Y-%m-%d %H:%M") {
+                    Ok(tm) => {
+                        let delay = time::Duration::minutes(minutes);
+                        let start = time::at_utc(tm.to_timespec() + delay);
+                        time::strftime("%Y-%m-%d %H:%M", &start).unwrap_or(now.to_owned())
+                    }
+                    Err(_) => now.to_owned(),
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -91,6 +283,167 @@ fn parse_intensity(input: &str) -> Result<Intensity, CliParseError> {
     }
 }
 
+fn parse_coords(input: &str) -> Result<(f32, f32), CliParseError> {
+    match *input.split(",").map(|x| str::parse::<f32>(x.trim())).collect::<Vec<_>>().as_slice() {
+        [Ok(lat), Ok(lon)] => Ok((lat, lon)),
+        _ => Err(CliParseError::ParseCoordsError(String::from(input))),
+    }
+}
+
+fn parse_overrides(matches: &clap::ArgMatches) -> Result<weather::Overrides, CliParseError> {
+    fn parse_opt<T, F>(value: Option<&str>, parse: F) -> Result<Option<T>, CliParseError>
+        where F: Fn(&str) -> Result<T, CliParseError>
+    {
+        match value {
+            None => Ok(None),
+            Some(v) => parse(v).map(Some),
+        }
+    }
+    Ok(weather::Overrides {
+        temp_c: try!(parse_opt(matches.value_of("temp"), parse_temperature)),
+        feelslike_c: try!(parse_opt(matches.value_of("feels-like"), parse_temperature)),
+        wind_kph: try!(parse_opt(matches.value_of("wind"), parse_wind_speed)),
+        condition: try!(parse_opt(matches.value_of("condition"), |c| {
+            weather::Condition::parse(c).ok_or(CliParseError::ParseOverrideError(String::from(c)))
+        })),
+        humidity: try!(parse_opt(matches.value_of("humidity"), parse_humidity)),
+        local_time: try!(parse_opt(matches.value_of("time"), parse_local_time)),
+    })
+}
+
+/// A temperature in celsius, or in fahrenheit with a trailing 'F' (e.g. '54F').
+fn parse_temperature(input: &str) -> Result<f32, CliParseError> {
+    let value = input.trim();
+    let (number, fahrenheit) = if value.ends_with('F') || value.ends_with('f') {
+        (&value[..value.len() - 1], true)
+    } else {
+        (value.trim_right_matches(|c: char| c == 'C' || c == 'c'), false)
+    };
+    match str::parse::<f32>(number.trim()) {
+        Ok(t) if fahrenheit => Ok((t - 32.0) * 5.0 / 9.0),
+        Ok(t) => Ok(t),
+        Err(_) => Err(CliParseError::ParseOverrideError(String::from(input))),
+    }
+}
+
+/// A temperature difference in celsius, or in fahrenheit with a trailing 'F'
+/// (e.g. '+3', '-2C' or '5F').
+fn parse_temperature_difference(input: &str) -> Option<f32> {
+    let value = input.trim();
+    let (number, fahrenheit) = if value.ends_with('F') || value.ends_with('f') {
+        (&value[..value.len() - 1], true)
+    } else {
+        (value.trim_right_matches(|c: char| c == 'C' || c == 'c'), false)
+    };
+    match str::parse::<f32>(number.trim().trim_left_matches('+')) {
+        Ok(d) if fahrenheit => Some(d * 5.0 / 9.0),
+        Ok(d) => Some(d),
+        Err(_) => None,
+    }
+}
+
+/// An elevation in meters, or in feet with a trailing 'ft' (e.g. '5900ft').
+fn parse_elevation(input: &str) -> Option<f32> {
+    let value = input.trim().to_lowercase();
+    let (number, feet) = if value.ends_with("ft") {
+        (&value[..value.len() - 2], true)
+    } else {
+        (value.trim_right_matches('m'), false)
+    };
+    match str::parse::<f32>(number.trim()) {
+        Ok(e) if feet => Some(e * 0.3048),
+        Ok(e) => Some(e),
+        Err(_) => None,
+    }
+}
+
+/// A wind speed in kilometer per hour, or in miles per hour with a trailing 'mph'.
+fn parse_wind_speed(input: &str) -> Result<f32, CliParseError> {
+    let value = input.trim().to_lowercase();
+    let (number, mph) = if value.ends_with("mph") {
+        (&value[..value.len() - 3], true)
+    } else {
+        (value.trim_right_matches("kph").trim_right_matches("km/h"), false)
+    };
+    match str::parse::<f32>(number.trim()) {
+        Ok(w) if w >= 0.0 && mph => Ok(w * 1.609344),
+        Ok(w) if w >= 0.0 => Ok(w),
+        _ => Err(CliParseError::ParseOverrideError(String::from(input))),
+    }
+}
+
+fn parse_humidity(input: &str) -> Result<u8, CliParseError> {
+    match str::parse::<u8>(input.trim().trim_right_matches('%')) {
+        Ok(h) if h <= 100 => Ok(h),
+        _ => Err(CliParseError::ParseOverrideError(String::from(input))),
+    }
+}
+
+/// Either `HH:MM` (today) or `YYYY-MM-DD HH:MM`.
+fn parse_local_time(input: &str) -> Result<String, CliParseError> {
+    let value = input.trim();
+    let full = if value.len() <= 5 {
+        let today = time::strftime("%Y-%m-%d", &time::now()).unwrap_or(String::new());
+        format!("{} {}", today, value)
+    } else {
+        value.to_owned()
+    };
+    match time::strptime(&full, "%Y-%m-%d %H:%M") {
+        Ok(tm) => Ok(time::strftime("%Y-%m-%d %H:%M", &tm).unwrap_or(full)),
+        Err(_) => Err(CliParseError::ParseOverrideError(String::from(input))),
+    }
+}
+
+/// A duration like '2h', '45m' or '1h30m', in minutes.
+fn parse_minutes(input: &str) -> Option<i64> {
+    let mut total = 0;
+    let mut number = String::new();
+    let mut seen_unit = false;
+    for c in input.trim().chars() {
+        if c.is_digit(10) {
+            number.push(c);
+            continue;
+        }
+        let value = match str::parse::<i64>(&number) {
+            Ok(v) => v,
+            Err(_) => return None,
+        };
+        number.clear();
+        match c {
+            'h' => total += value * 60,
+            'm' => total += value,
+            _ => return None,
+        }
+        seen_unit = true;
+    }
+    if !number.is_empty() || !seen_unit {
+        return None;
+    }
+    Some(total)
+}
+
+/// Either `HH:MM` (today) or `YYYY-MM-DD HH:MM`.
+fn parse_start(input: &str) -> Result<Start, CliParseError> {
+    let value = input.trim();
+    let invalid = |_| CliParseError::ParseStartError(String::from(input));
+    if value.len() <= 5 {
+        time::strptime(value, "%H:%M")
+            .map(|tm| Start::Next(format!("{:02}:{:02}", tm.tm_hour, tm.tm_min)))
+            .map_err(invalid)
+    } else {
+        time::strptime(value, "%Y-%m-%d %H:%M")
+            .map(|tm| Start::At(time::strftime("%Y-%m-%d %H:%M", &tm).unwrap_or(value.to_owned())))
+            .map_err(invalid)
+    }
+}
+
+/// A delay from now, e.g. '2h'.
+fn parse_delay(input: &str) -> Result<Start, CliParseError> {
+    parse_minutes(input)
+        .map(Start::In)
+        .ok_or(CliParseError::ParseStartError(String::from(input)))
+}
+
 pub fn cli() -> App<'static, 'static> {
     let gender_arg = Arg::with_name("gender")
         .long("gender")
@@ -104,18 +457,255 @@ pub fn cli() -> App<'static, 'static> {
         .value_name("CITY")
         .help("The city you are in right now.")
         .required(false);
+    let coords_arg = Arg::with_name("coords")
+        .long("coords")
+        .value_name("LAT,LON")
+        .help("The coordinates you are at, in decimal degrees (e.g. '37.79,12.43').")
+        .required(false);
     let intensity_arg = Arg::with_name("intensity")
         .long("intensity")
         .short("i")
         .value_name("INTENSITY")
         .help("easy run|long run|hard workout|race")
         .required(true);
+    let provider_arg = Arg::with_name("provider")
+        .long("provider")
+        .short("p")
+        .value_name("PROVIDER")
+        .help("The weather provider to use (defaults to 'apixu').")
+        .possible_values(&weather::PROVIDERS)
+        .required(false);
+    let profile_arg = Arg::with_name("profile")
+        .long("profile")
+        .value_name("PROFILE")
+        .help("The [profile.<name>] section of the config file to use.")
+        .required(false);
+    let metar_arg = Arg::with_name("metar")
+        .long("metar")
+        .value_name("REPORT")
+        .help("Use the given METAR report instead of asking a weather provider.")
+        .conflicts_with("metar-file")
+        .required(false);
+    let metar_file_arg = Arg::with_name("metar-file")
+        .long("metar-file")
+        .value_name("PATH")
+        .help("Use the last METAR report in the given station file.")
+        .required(false);
+    let weather_file_arg = Arg::with_name("weather-file")
+        .long("weather-file")
+        .value_name("PATH")
+        .help("Use the weather in the given file (a saved Apixu response, or TOML).")
+        .conflicts_with_all(&["metar", "metar-file"])
+        .required(false);
+    let temp_arg = Arg::with_name("temp")
+        .long("temp")
+        .value_name("TEMP")
+        .help("The temperature, in celsius (or fahrenheit, e.g. '54F').")
+        .required(false);
+    let feels_like_arg = Arg::with_name("feels-like")
+        .long("feels-like")
+        .value_name("TEMP")
+        .help("The feels like temperature, in celsius (or fahrenheit, e.g. '50F').")
+        .required(false);
+    let wind_arg = Arg::with_name("wind")
+        .long("wind")
+        .value_name("SPEED")
+        .help("The wind speed, in km/h (or mph, e.g. '10mph').")
+        .required(false);
+    let condition_arg = Arg::with_name("condition")
+        .long("condition")
+        .value_name("CONDITION")
+        .help("The weather condition.")
+        .possible_values(&weather::CONDITIONS)
+        .required(false);
+    let humidity_arg = Arg::with_name("humidity")
+        .long("humidity")
+        .value_name("PERCENT")
+        .help("The relative humidity, as percentage.")
+        .required(false);
+    let time_arg = Arg::with_name("time")
+        .long("time")
+        .value_name("TIME")
+        .help("The local time of the run, as 'HH:MM' or 'YYYY-MM-DD HH:MM'.")
+        .required(false);
+    let at_arg = Arg::with_name("at")
+        .long("at")
+        .value_name("TIME")
+        .help("When you plan to run, as 'HH:MM' (the next one) or 'YYYY-MM-DD HH:MM', using the \
+               forecast.")
+        .required(false);
+    let in_arg = Arg::with_name("in")
+        .long("in")
+        .value_name("DELAY")
+        .help("How long until you run (e.g. '2h' or '1h30m'), using the forecast.")
+        .conflicts_with("at")
+        .required(false);
+    let duration_arg = Arg::with_name("duration")
+        .long("duration")
+        .short("d")
+        .value_name("DURATION")
+        .help("How long you plan to run (e.g. '90m' or '1h30m'), to dress for the whole run.")
+        .required(false);
+    let engine_arg = Arg::with_name("engine")
+        .long("engine")
+        .short("e")
+        .value_name("ENGINE")
+        .help("Who recommends what to wear: viktor itself ('local', the default) or runnersworld.com.")
+        .possible_values(&["local", "runners-world"])
+        .required(false);
+    let rules_arg = Arg::with_name("rules")
+        .long("rules")
+        .value_name("PATH")
+        .help("Dress by the clothing rules in the given TOML file, instead of the bundled ones.")
+        .required(false);
+    let thermal_offset_arg = Arg::with_name("thermal-offset")
+        .long("thermal-offset")
+        .value_name("DEGREES")
+        .help("How much warmer you feel than most when running, in celsius (or fahrenheit, e.g. \
+               '5F'). Use '--thermal-offset=-3' if you run cold.")
+        .required(false);
+    let elevation_arg = Arg::with_name("elevation")
+        .long("elevation")
+        .value_name("HEIGHT")
+        .help("How high you run, in meters (or feet, e.g. '5900ft'), if higher or lower than \
+               the weather station.")
+        .required(false);
+    let station_elevation_arg = Arg::with_name("station-elevation")
+        .long("station-elevation")
+        .value_name("HEIGHT")
+        .help("How high the weather station is, in meters (or feet), if the provider doesn't \
+               tell it. Needed by '--elevation' and '--route' then.")
+        .required(false);
+    let route_arg = Arg::with_name("route")
+        .long("route")
+        .value_name("PATH")
+        .help("A GPX file of your route, to dress for its highest point.")
+        .conflicts_with("elevation")
+        .required(false);
+    let dump_html_arg = Arg::with_name("dump-html")
+        .long("dump-html")
+        .value_name("PATH")
+        .help("Save the Runner's World page to the given file if it can't be understood.")
+        .required(false);
+    let explain_arg = Arg::with_name("explain")
+        .long("explain")
+        .help("Tell how the weather was turned into the answers of the Runner's World form.")
+        .required(false);
     let app = App::new("Viktor")
         .version("0.0.1")
         .author("Alfredo Di Napoli")
         .about("Display on iTerm what to wear while running")
         .arg(city_arg)
+        .arg(coords_arg)
         .arg(intensity_arg)
-        .arg(gender_arg);
+        .arg(gender_arg)
+        .arg(provider_arg)
+        .arg(profile_arg)
+        .arg(metar_arg)
+        .arg(metar_file_arg)
+        .arg(weather_file_arg)
+        .arg(temp_arg)
+        .arg(feels_like_arg)
+        .arg(wind_arg)
+        .arg(condition_arg)
+        .arg(humidity_arg)
+        .arg(time_arg)
+        .arg(at_arg)
+        .arg(in_arg)
+        .arg(duration_arg)
+        .arg(engine_arg)
+        .arg(rules_arg)
+        .arg(thermal_offset_arg)
+        .arg(elevation_arg)
+        .arg(station_elevation_arg)
+        .arg(route_arg)
+        .arg(dump_html_arg)
+        .arg(explain_arg)
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(feedback_cli());
     app
 }
+
+/// `viktor feedback`, to tell how the last run felt.
+fn feedback_cli() -> App<'static, 'static> {
+    SubCommand::with_name("feedback")
+        .about("Tell viktor how you felt in its last recommendation, so that it learns.")
+        .arg(Arg::with_name("too-cold").long("too-cold").help("You were too cold."))
+        .arg(Arg::with_name("too-warm").long("too-warm").help("You were too warm."))
+        .arg(Arg::with_name("just-right").long("just-right").help("You were just fine."))
+        .group(ArgGroup::with_name("comfort")
+            .args(&["too-cold", "too-warm", "just-right"])
+            .required(true))
+        .arg(Arg::with_name("body-part")
+            .long("body-part")
+            .value_name("BODY_PART")
+            .help("Only this body part felt so, e.g. 'hands'.")
+            .possible_values(&["head", "torso", "hands", "legs", "feet"])
+            .required(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_temperatures_in_both_scales() {
+        assert_eq!(parse_temperature("12").unwrap(), 12.0);
+        assert_eq!(parse_temperature("-3C").unwrap(), -3.0);
+        assert_eq!(parse_temperature("50F").unwrap(), 10.0);
+        assert!(parse_temperature("warm").is_err());
+    }
+
+    #[test]
+    fn can_parse_temperature_differences() {
+        assert_eq!(parse_temperature_difference("+3"), Some(3.0));
+        assert_eq!(parse_temperature_difference("-2C"), Some(-2.0));
+        assert_eq!(parse_temperature_difference("-9F"), Some(-5.0));
+        assert_eq!(parse_temperature_difference("hot"), None);
+    }
+
+    #[test]
+    fn can_parse_elevations_in_both_units() {
+        assert_eq!(parse_elevation("1800m"), Some(1800.0));
+        assert_eq!(parse_elevation("1800"), Some(1800.0));
+        assert!((parse_elevation("5900ft").unwrap() - 1798.3).abs() < 0.1);
+        assert_eq!(parse_elevation("high"), None);
+    }
+
+    #[test]
+    fn can_parse_wind_speeds() {
+        assert_eq!(parse_wind_speed("20").unwrap(), 20.0);
+        assert_eq!(parse_wind_speed("20 km/h").unwrap(), 20.0);
+        assert!((parse_wind_speed("10mph").unwrap() - 16.09).abs() < 0.01);
+        assert!(parse_wind_speed("-5").is_err());
+    }
+
+    #[test]
+    fn can_parse_the_local_time() {
+        assert_eq!(parse_local_time("2026-10-19 06:30").unwrap(), "2026-10-19 06:30");
+        assert!(parse_local_time("06:30").unwrap().ends_with(" 06:30"));
+        assert!(parse_local_time("tomorrow").is_err());
+        assert!(parse_humidity("101").is_err());
+    }
+
+    #[test]
+    fn the_start_is_resolved_in_the_local_time_of_the_place() {
+        assert_eq!(parse_start("2026-10-19 06:30").unwrap(),
+                   Start::At("2026-10-19 06:30".to_owned()));
+        assert!(parse_start("tomorrow").is_err());
+        let now = "2026-10-18 23:15";
+        assert_eq!(parse_start("06:30").unwrap().resolve(now), "2026-10-19 06:30");
+        assert_eq!(parse_start("23:45").unwrap().resolve(now), "2026-10-18 23:45");
+        assert_eq!(parse_delay("2h").unwrap().resolve(now), "2026-10-19 01:15");
+        assert_eq!(parse_start("2026-10-19 06:30").unwrap().resolve(now), "2026-10-19 06:30");
+    }
+
+    #[test]
+    fn can_parse_delays() {
+        assert_eq!(parse_minutes("2h"), Some(120));
+        assert_eq!(parse_minutes("45m"), Some(45));
+        assert_eq!(parse_minutes("1h30m"), Some(90));
+        assert_eq!(parse_minutes("30"), None);
+        assert_eq!(parse_minutes("2d"), None);
+    }
+}
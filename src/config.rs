@@ -0,0 +1,220 @@
+extern crate toml;
+
+use std;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::path::PathBuf;
+
+/// The settings which can be given at the top level of the config file,
+/// as well as overridden in a `[profile.<name>]` table.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Settings {
+    /// The weather provider to use when `--provider` is not given.
+    pub provider: Option<String>,
+    /// "local" or "runners-world", when `--engine` is not given.
+    pub engine: Option<String>,
+    /// The clothing rules file of the local engine, when `--rules` is not given.
+    pub rules: Option<String>,
+    /// How much warmer (or colder, if negative) than others you feel when
+    /// running, in celsius, when `--thermal-offset` is not given.
+    pub thermal_offset: Option<f32>,
+    /// How many hours the Runner's World pages are cached for (0 to not
+    /// cache them).
+    pub runners_world_cache_hours: Option<i64>,
+    /// Above which elevation, in meters, the wind blows unsheltered by trees.
+    pub treeline_m: Option<f32>,
+    /// How high the weather station is, in meters, when the provider doesn't
+    /// tell it and `--station-elevation` is not given.
+    pub station_elevation_m: Option<f32>,
+    /// API key for OpenWeatherMap, if not given via OPENWEATHERMAP_API_KEY.
+    pub openweathermap_api_key: Option<String>,
+    /// "standard", "metric" or "imperial".
+    pub openweathermap_units: Option<String>,
+    /// An email or url MET Norway can reach you at, sent in the User-Agent.
+    pub met_norway_contact: Option<String>,
+    /// An email or url the NWS can reach you at, sent in the User-Agent.
+    pub nws_contact: Option<String>,
+}
+
+impl Settings {
+    /// Overrides these settings with the ones set in `other`.
+    pub fn merge(&self, other: &Settings) -> Settings {
+        Settings {
+            provider: other.provider.clone().or(self.provider.clone()),
+            engine: other.engine.clone().or(self.engine.clone()),
+            rules: other.rules.clone().or(self.rules.clone()),
+            thermal_offset: other.thermal_offset.or(self.thermal_offset),
+            runners_world_cache_hours: other.runners_world_cache_hours
+                .or(self.runners_world_cache_hours),
+            treeline_m: other.treeline_m.or(self.treeline_m),
+            station_elevation_m: other.station_elevation_m.or(self.station_elevation_m),
+            openweathermap_api_key: other.openweathermap_api_key
+                .clone()
+                .or(self.openweathermap_api_key.clone()),
+            openweathermap_units: other.openweathermap_units
+                .clone()
+                .or(self.openweathermap_units.clone()),
+            met_norway_contact: other.met_norway_contact.clone().or(self.met_norway_contact.clone()),
+            nws_contact: other.nws_contact.clone().or(self.nws_contact.clone()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Profiles {
+    profile: Option<BTreeMap<String, Settings>>,
+}
+
+/// The user configuration, read from `$XDG_CONFIG_HOME/viktor/config.toml`
+/// (or the file pointed by VIKTOR_CONFIG).
+#[derive(Debug, Default)]
+pub struct Config {
+    pub defaults: Settings,
+    pub profiles: BTreeMap<String, Settings>,
+}
+
+impl Config {
+    /// The settings to use for the given profile, if any.
+    pub fn settings(&self, profile: &Option<String>) -> Result<Settings, ConfigError> {
+        match *profile {
+            None => Ok(self.defaults.clone()),
+            Some(ref name) => {
+                self.profiles
+                    .get(name)
+                    .map(|p| self.defaults.merge(p))
+                    .ok_or(ConfigError::UnknownProfile(name.clone()))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IOError(std::io::Error),
+    ParseError(String),
+    UnknownProfile(String),
+}
+
+impl From<std::io::Error> for ConfigError {
+    fn from(err: std::io::Error) -> ConfigError {
+        ConfigError::IOError(err)
+    }
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    if let Ok(p) = env::var("VIKTOR_CONFIG") {
+        return Some(PathBuf::from(p));
+    }
+    let config_home = match env::var("XDG_CONFIG_HOME") {
+        Ok(d) => Some(PathBuf::from(d)),
+        Err(_) => env::home_dir().map(|h| h.join(".config")),
+    };
+    config_home.map(|d| d.join("viktor").join("config.toml"))
+}
+
+/// Where viktor caches responses, i.e. `$XDG_CACHE_HOME/viktor`.
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache_home = match env::var("XDG_CACHE_HOME") {
+        Ok(d) => Some(PathBuf::from(d)),
+        Err(_) => env::home_dir().map(|h| h.join(".cache")),
+    };
+    cache_home.map(|d| d.join("viktor"))
+}
+
+/// Where viktor keeps what it learns about the user, i.e.
+/// `$XDG_DATA_HOME/viktor`.
+pub fn data_dir() -> Option<PathBuf> {
+    let data_home = match env::var("XDG_DATA_HOME") {
+        Ok(d) => Some(PathBuf::from(d)),
+        Err(_) => env::home_dir().map(|h| h.join(".local").join("share")),
+    };
+    data_home.map(|d| d.join("viktor"))
+}
+
+/// Loads the user configuration, falling back to the default one if
+/// there is no config file.
+pub fn load() -> Result<Config, ConfigError> {
+    match config_path() {
+        Some(ref path) if path.exists() => {
+            let mut contents = String::new();
+            let mut file = try!(File::open(path));
+            let _ = try!(file.read_to_string(&mut contents));
+            parse(&contents)
+        }
+        _ => Ok(Config::default()),
+    }
+}
+
+pub fn parse(contents: &str) -> Result<Config, ConfigError> {
+    let table = try!(parse_toml(contents));
+    let defaults: Settings = try!(decode(table.clone()));
+    let profiles: Profiles = try!(decode(table));
+    Ok(Config {
+        defaults: defaults,
+        profiles: profiles.profile.unwrap_or(BTreeMap::new()),
+    })
+}
+
+/// Parses a TOML document, reporting syntax errors with their line and column.
+pub fn parse_toml(contents: &str) -> Result<toml::Table, ConfigError> {
+    let mut parser = toml::Parser::new(contents);
+    match parser.parse() {
+        Some(table) => Ok(table),
+        None => {
+            let desc = parser.errors
+                .iter()
+                .map(|e| {
+                    let (line, col) = parser.to_linecol(e.lo);
+                    format!("line {}, column {}: {}", line + 1, col + 1, e.desc)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            Err(ConfigError::ParseError(desc))
+        }
+    }
+}
+
+pub fn decode<T: ::serde::Deserialize>(table: toml::Table) -> Result<T, ConfigError> {
+    let mut decoder = toml::Decoder::new(toml::Value::Table(table));
+    ::serde::Deserialize::deserialize(&mut decoder)
+        .map_err(|e| ConfigError::ParseError(format!("{}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_the_provider() {
+        let cfg = parse("provider = \"apixu\"\n").unwrap();
+        assert_eq!(cfg.defaults.provider, Some("apixu".to_owned()));
+    }
+
+    #[test]
+    fn profiles_override_the_defaults() {
+        let cfg = parse(r#"
+provider = "apixu"
+openweathermap_units = "metric"
+
+[profile.owm]
+provider = "openweathermap"
+openweathermap_api_key = "xxxxxx"
+"#)
+            .unwrap();
+        let settings = cfg.settings(&Some("owm".to_owned())).unwrap();
+        assert_eq!(settings.provider, Some("openweathermap".to_owned()));
+        assert_eq!(settings.openweathermap_api_key, Some("xxxxxx".to_owned()));
+        assert_eq!(settings.openweathermap_units, Some("metric".to_owned()));
+        assert!(cfg.settings(&Some("nope".to_owned())).is_err());
+    }
+
+    #[test]
+    fn reports_the_line_of_syntax_errors() {
+        match parse("\nprovider = apixu\n") {
+            Err(ConfigError::ParseError(e)) => assert!(e.starts_with("line 2")),
+            _ => panic!("Expected a ParseError"),
+        }
+    }
+}
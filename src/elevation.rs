@@ -0,0 +1,115 @@
+//! The weather up where you run, out of the weather at the station.
+
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+use weather::Observation;
+
+/// How much colder it gets per kilometer of climb, in celsius: the standard
+/// atmosphere's environmental lapse rate.
+pub static LAPSE_RATE_C_PER_KM: f32 = 6.5;
+
+/// Where the trees stop sheltering you from the wind, in meters, unless the
+/// config file says otherwise. It's about right for the Alps and the Rockies,
+/// much lower up north.
+pub static DEFAULT_TREELINE_M: f32 = 2000.0;
+
+/// How much windier it is on open ridges and summits than down at the
+/// station.
+static EXPOSED_WIND_FACTOR: f32 = 1.5;
+
+#[derive(Debug)]
+pub enum RouteError {
+    IOError(io::Error),
+    /// The route has no `<ele>` points to take the elevation from.
+    NoElevation(String),
+}
+
+impl From<io::Error> for RouteError {
+    fn from(err: io::Error) -> RouteError {
+        RouteError::IOError(err)
+    }
+}
+
+/// The weather at `target_m` meters, given the one observed at a station
+/// `station_m` meters high.
+pub fn at_elevation(weather: &Observation,
+                    station_m: f32,
+                    target_m: f32,
+                    treeline_m: f32)
+                    -> Observation {
+    let delta_c = -LAPSE_RATE_C_PER_KM * (target_m - station_m) / 1000.0;
+    let mut adjusted = weather.clone();
+    adjusted.temp_c = weather.temp_c + delta_c;
+    adjusted.feelslike_c = weather.feelslike_c + delta_c;
+    // The air keeps (roughly) its relative humidity as it cools, not its dew point.
+    adjusted.humidity = weather.relative_humidity().map(|h| h.round() as u8);
+    adjusted.dew_point_c = None;
+    if target_m > treeline_m {
+        adjusted.wind_kph = weather.wind_kph * EXPOSED_WIND_FACTOR;
+        adjusted.gust_kph = weather.gust_kph.map(|g| g * EXPOSED_WIND_FACTOR);
+    }
+    adjusted.elevation_m = Some(target_m);
+    adjusted
+}
+
+/// The highest point of a GPX route, in meters.
+pub fn route_summit(path: &Path) -> Result<f32, RouteError> {
+    let mut gpx = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut gpx)));
+    highest_point(&gpx).ok_or(RouteError::NoElevation(path.display().to_string()))
+}
+
+fn highest_point(gpx: &str) -> Option<f32> {
+    gpx.split("<ele>")
+        .skip(1)
+        .filter_map(|s| s.split("</ele>").next())
+        .filter_map(|e| str::parse::<f32>(e.trim()).ok())
+        .fold(None, |max, e| match max {
+            Some(m) if m >= e => Some(m),
+            _ => Some(e),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weather::Observation;
+
+    #[test]
+    fn it_gets_colder_and_windier_up_the_mountain() {
+        let valley = Observation {
+            temp_c: 15.0,
+            feelslike_c: 15.0,
+            wind_kph: 10.0,
+            humidity: Some(60),
+            elevation_m: Some(500.0),
+            ..Default::default()
+        };
+        let forest = at_elevation(&valley, 500.0, 1500.0, DEFAULT_TREELINE_M);
+        assert!((forest.temp_c - 8.5).abs() < 0.01);
+        assert_eq!(forest.wind_kph, 10.0);
+        assert_eq!(forest.humidity, Some(60));
+        assert_eq!(forest.elevation_m, Some(1500.0));
+
+        let summit = at_elevation(&valley, 500.0, 2500.0, DEFAULT_TREELINE_M);
+        assert!((summit.temp_c - 2.0).abs() < 0.01);
+        assert_eq!(summit.wind_kph, 15.0);
+
+        let by_hand = Observation { temp_c: 15.0, ..Default::default() };
+        let up = at_elevation(&by_hand, 200.0, 1200.0, DEFAULT_TREELINE_M);
+        assert!((up.temp_c - 8.5).abs() < 0.01);
+        assert_eq!(up.elevation_m, Some(1200.0));
+    }
+
+    #[test]
+    fn the_route_summit_is_its_highest_point() {
+        let gpx = "<trkpt lat=\"46.0\" lon=\"7.7\"><ele>1620.5</ele></trkpt>\n\
+                   <trkpt lat=\"46.1\" lon=\"7.7\"><ele>2210.0</ele></trkpt>\n\
+                   <trkpt lat=\"46.2\" lon=\"7.7\"><ele>1980</ele></trkpt>";
+        assert_eq!(highest_point(gpx), Some(2210.0));
+        assert_eq!(highest_point("<trkpt lat=\"46.0\" lon=\"7.7\"></trkpt>"), None);
+    }
+}
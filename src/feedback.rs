@@ -0,0 +1,272 @@
+//! What the user told us after their runs, and how it moves the clothing
+//! thresholds of the local engine.
+
+use std;
+use std::cmp;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use cache;
+use recommendation::{BodyPart, Recommendation};
+use weather::Observation;
+
+/// How much a single feedback moves the thresholds, in fahrenheit.
+static STEP_F: i32 = 2;
+
+/// How far the thresholds can move, in fahrenheit.
+static MAX_OFFSET_F: i32 = 15;
+
+/// How many feedbacks are remembered: older ones are forgotten, so that
+/// viktor follows you as your habits change.
+static MEMORY: usize = 20;
+
+/// How far from the temperature of a run, in fahrenheit, its feedback still
+/// counts: feeling cold at 30F says nothing about what to wear at 70F.
+static NEARBY_F: i32 = 10;
+
+static LAST_RUN_FILE: &'static str = "last_run.json";
+static HISTORY_FILE: &'static str = "feedback.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comfort {
+    TooCold,
+    JustRight,
+    TooWarm,
+}
+
+impl Comfort {
+    pub fn parse(input: &str) -> Option<Comfort> {
+        match input {
+            "too-cold" => Some(Comfort::TooCold),
+            "just-right" => Some(Comfort::JustRight),
+            "too-warm" => Some(Comfort::TooWarm),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Comfort::TooCold => "too-cold",
+            Comfort::JustRight => "just-right",
+            Comfort::TooWarm => "too-warm",
+        }
+    }
+
+    /// Which way the thresholds move: feeling cold means dressing warmer
+    /// next time, i.e. wearing the warmer garments up to higher temperatures.
+    fn vote(&self) -> i32 {
+        match *self {
+            Comfort::TooCold => 1,
+            Comfort::JustRight => 0,
+            Comfort::TooWarm => -1,
+        }
+    }
+}
+
+/// A garment of the last recommendation.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WornGarment {
+    /// e.g. "hands"
+    pub body_part: String,
+    pub name: String,
+}
+
+/// When the last recommendation was given, for which temperature and what
+/// it recommended.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct LastRun {
+    pub local_time: String,
+    pub temp_c: f32,
+    /// The temperature the local engine dressed for, in fahrenheit
+    pub effective_temp_f: i32,
+    /// Missing from the runs remembered by older versions
+    #[serde(default)]
+    pub items: Vec<WornGarment>,
+}
+
+impl LastRun {
+    pub fn new(weather: &Observation,
+               effective_temp_f: i32,
+               recommendation: &Recommendation)
+               -> LastRun {
+        LastRun {
+            local_time: weather.local_time.clone(),
+            temp_c: weather.temp_c,
+            effective_temp_f: effective_temp_f,
+            items: recommendation.items
+                .iter()
+                .map(|g| {
+                    WornGarment {
+                        body_part: g.body_part.name().to_owned(),
+                        name: g.name.clone(),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// How a run felt, overall or for one body part.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Feedback {
+    /// "too-cold", "just-right" or "too-warm"
+    pub comfort: String,
+    /// The body part it is about, or none for the whole body
+    pub body_part: Option<String>,
+    pub run: LastRun,
+}
+
+impl Feedback {
+    fn is_about(&self, body_part: BodyPart) -> bool {
+        self.body_part.as_ref().map(|b| b == body_part.name()).unwrap_or(true)
+    }
+
+    fn is_near(&self, effective_temp_f: i32) -> bool {
+        (self.run.effective_temp_f - effective_temp_f).abs() <= NEARBY_F
+    }
+}
+
+#[derive(Debug)]
+pub enum FeedbackError {
+    IOError(std::io::Error),
+    /// There is no recommendation to give feedback about yet.
+    NoLastRun,
+}
+
+impl From<std::io::Error> for FeedbackError {
+    fn from(err: std::io::Error) -> FeedbackError {
+        FeedbackError::IOError(err)
+    }
+}
+
+/// Remembers the recommendation just given, for the next feedback.
+pub fn record_run(dir: &Path, run: &LastRun) -> Result<(), std::io::Error> {
+    cache::write_json(&dir.join(LAST_RUN_FILE), run)
+}
+
+/// Everything the user told us so far, oldest first.
+pub fn history(dir: &Path) -> Vec<Feedback> {
+    cache::read_json(&dir.join(HISTORY_FILE)).unwrap_or(Vec::new())
+}
+
+/// Records how the last run felt, returning the (remembered) history.
+pub fn give(dir: &Path,
+            comfort: Comfort,
+            body_part: Option<BodyPart>)
+            -> Result<Vec<Feedback>, FeedbackError> {
+    let run: LastRun = try!(cache::read_json(&dir.join(LAST_RUN_FILE))
+        .ok_or(FeedbackError::NoLastRun));
+    let mut history = history(dir);
+    history.push(Feedback {
+        comfort: comfort.name().to_owned(),
+        body_part: body_part.map(|b| b.name().to_owned()),
+        run: run,
+    });
+    if history.len() > MEMORY {
+        let forgotten = history.len() - MEMORY;
+        history.drain(..forgotten);
+    }
+    try!(cache::write_json(&dir.join(HISTORY_FILE), &history));
+    Ok(history)
+}
+
+/// How much to move the thresholds of each body part, in fahrenheit, to
+/// dress for `effective_temp_f`: out of the latest feedbacks about it, given
+/// after runs at about the same temperature. Feeling right doesn't move
+/// them, but pushes older complaints out of memory.
+pub fn offsets(history: &[Feedback], effective_temp_f: i32) -> BTreeMap<BodyPart, i32> {
+    let mut offsets = BTreeMap::new();
+    for body_part in vec![BodyPart::Head, BodyPart::Torso, BodyPart::Hands, BodyPart::Legs,
+                          BodyPart::Feet] {
+        let votes: i32 = history.iter()
+            .rev()
+            .take(MEMORY)
+            .filter(|f| f.is_about(body_part) && f.is_near(effective_temp_f))
+            .filter_map(|f| Comfort::parse(&f.comfort))
+            .map(|c| c.vote())
+            .sum();
+        let offset = cmp::max(-MAX_OFFSET_F, cmp::min(MAX_OFFSET_F, votes * STEP_F));
+        if offset != 0 {
+            offsets.insert(body_part, offset);
+        }
+    }
+    offsets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use recommendation::{BodyPart, Garment, Recommendation};
+    use weather::Observation;
+
+    fn feedback(comfort: Comfort, body_part: Option<BodyPart>) -> Feedback {
+        Feedback {
+            comfort: comfort.name().to_owned(),
+            body_part: body_part.map(|b| b.name().to_owned()),
+            run: LastRun { effective_temp_f: 45, ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn learns_which_body_parts_run_cold() {
+        let history = vec![feedback(Comfort::TooCold, Some(BodyPart::Hands)),
+                           feedback(Comfort::TooCold, Some(BodyPart::Hands)),
+                           feedback(Comfort::TooWarm, None)];
+        let offsets = offsets(&history, 45);
+        assert_eq!(offsets.get(&BodyPart::Hands), Some(&2));
+        assert_eq!(offsets.get(&BodyPart::Torso), Some(&-2));
+        assert_eq!(offsets.get(&BodyPart::Feet), Some(&-2));
+    }
+
+    #[test]
+    fn forgets_old_feedback() {
+        let mut history = vec![feedback(Comfort::TooCold, None); 30];
+        assert_eq!(offsets(&history, 45).get(&BodyPart::Legs), Some(&15));
+        history.extend(vec![feedback(Comfort::JustRight, None); 20]);
+        assert!(offsets(&history, 45).is_empty());
+    }
+
+    #[test]
+    fn only_runs_at_about_the_same_temperature_count() {
+        let history = vec![feedback(Comfort::TooCold, Some(BodyPart::Hands))];
+        assert_eq!(offsets(&history, 50).get(&BodyPart::Hands), Some(&2));
+        assert!(offsets(&history, 70).is_empty());
+    }
+
+    #[test]
+    fn records_the_feedback_about_the_last_run() {
+        let dir = env::temp_dir().join("viktor-test-feedback");
+        let _ = fs::remove_dir_all(&dir);
+        match give(&dir, Comfort::TooCold, None) {
+            Err(FeedbackError::NoLastRun) => (),
+            other => panic!("Expected NoLastRun, got {:?}", other),
+        }
+
+        let weather = Observation { temp_c: 6.0, ..Default::default() };
+        let gloves = Garment {
+            body_part: BodyPart::Hands,
+            name: "Gloves".to_owned(),
+            description: "Light running gloves.".to_owned(),
+            image: None,
+        };
+        let run = LastRun::new(&weather, 43, &Recommendation::new(vec![gloves]));
+        record_run(&dir, &run).unwrap();
+        give(&dir, Comfort::TooCold, Some(BodyPart::Hands)).unwrap();
+        let feedbacks = give(&dir, Comfort::JustRight, None).unwrap();
+
+        assert_eq!(feedbacks, history(&dir));
+        assert_eq!(feedbacks.len(), 2);
+        assert_eq!(feedbacks[0].body_part, Some("hands".to_owned()));
+        assert_eq!(feedbacks[0].run, run);
+        assert_eq!(feedbacks[0].run.items,
+                   vec![WornGarment { body_part: "hands".to_owned(), name: "Gloves".to_owned() }]);
+
+        for _ in 0..MEMORY {
+            give(&dir, Comfort::TooWarm, None).unwrap();
+        }
+        assert_eq!(history(&dir).len(), MEMORY);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,172 @@
+extern crate serde_json;
+extern crate time;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use apixu_weather;
+use config::{self, ConfigError};
+use weather::{is_daytime, parse_hours_from_local_time, Client, Condition, Observation, Place,
+              Query, WeatherError, WeatherProvider};
+
+/// The hand-written form of a weather file, e.g.:
+///
+/// ```toml
+/// city = "Marsala"
+/// time = "2026-10-18 07:30"
+/// temp_c = 12.5
+/// wind_kph = 15
+/// condition = "light-rain"
+/// ```
+///
+/// Only `temp_c` is mandatory.
+#[derive(Deserialize, Debug, Default)]
+pub struct ManualWeather {
+    pub city: Option<String>,
+    pub country: Option<String>,
+    /// Local date and time, as `YYYY-MM-DD HH:MM`. Defaults to now.
+    pub time: Option<String>,
+    pub temp_c: f32,
+    /// Defaults to `temp_c`
+    pub feelslike_c: Option<f32>,
+    pub wind_kph: Option<f32>,
+    pub gust_kph: Option<f32>,
+    pub visibility_km: Option<f32>,
+    pub dew_point_c: Option<f32>,
+    pub humidity: Option<u8>,
+    pub cloud: Option<u8>,
+    pub precip_mm: Option<f32>,
+    /// Probability of precipitation as percentage
+    pub precip_chance: Option<u8>,
+    pub uv_index: Option<f32>,
+    /// Guessed from `time` if not given.
+    pub is_day: Option<bool>,
+    /// One of `weather::CONDITIONS`, defaults to "clear".
+    pub condition: Option<String>,
+}
+
+pub fn to_observation(manual: &ManualWeather) -> Result<Observation, WeatherError> {
+    let local_time = match manual.time {
+        Some(ref t) => t.clone(),
+        None => time::strftime("%Y-%m-%d %H:%M", &time::now()).unwrap_or(String::new()),
+    };
+    let hour = match parse_hours_from_local_time(&local_time) {
+        Some(h) => h,
+        None => return Err(WeatherError::InvalidWeatherFile(format!("time: {}", local_time))),
+    };
+    let condition_name = manual.condition.clone().unwrap_or("clear".to_owned());
+    let condition = match Condition::parse(&condition_name) {
+        Some(c) => c,
+        None => {
+            return Err(WeatherError::InvalidWeatherFile(format!("condition: {}", condition_name)))
+        }
+    };
+    Ok(Observation {
+        place: Place {
+            name: manual.city.clone().unwrap_or(String::new()),
+            country: manual.country.clone().unwrap_or(String::new()),
+            utc_offset_s: None,
+        },
+        local_time: local_time,
+        temp_c: manual.temp_c,
+        feelslike_c: manual.feelslike_c.unwrap_or(manual.temp_c),
+        wind_kph: manual.wind_kph.unwrap_or(0.0),
+        gust_kph: manual.gust_kph,
+        visibility_km: manual.visibility_km,
+        dew_point_c: manual.dew_point_c,
+        humidity: manual.humidity,
+        cloud: manual.cloud,
+        precip_mm: manual.precip_mm,
+        precip_chance: manual.precip_chance,
+        uv_index: manual.uv_index,
+        elevation_m: None,
+        is_day: manual.is_day.unwrap_or(is_daytime(hour)),
+        condition: condition,
+        condition_text: condition_name,
+    })
+}
+
+/// Decodes a weather file, either a saved Apixu response (JSON) or the
+/// hand-written TOML form.
+pub fn parse(contents: &str) -> Result<Observation, WeatherError> {
+    if contents.trim_left().starts_with('{') {
+        let cw: apixu_weather::CurrentWeather = try!(serde_json::from_str(contents));
+        return Ok(apixu_weather::to_observation(&cw));
+    }
+    let manual: ManualWeather = try!(config::parse_toml(contents)
+        .and_then(config::decode::<ManualWeather>)
+        .map_err(|e| match e {
+            ConfigError::ParseError(desc) => WeatherError::InvalidWeatherFile(desc),
+            other => WeatherError::InvalidWeatherFile(format!("{:?}", other)),
+        }));
+    to_observation(&manual)
+}
+
+/// Serves the weather stored in a local file, without any network access.
+pub struct WeatherFile {
+    observation: Observation,
+}
+
+impl WeatherFile {
+    pub fn open(path: &Path) -> Result<WeatherFile, WeatherError> {
+        let mut contents = String::new();
+        let mut file = try!(File::open(path));
+        let _ = try!(file.read_to_string(&mut contents));
+        parse(&contents).map(|o| WeatherFile { observation: o })
+    }
+}
+
+impl WeatherProvider for WeatherFile {
+    fn name(&self) -> &'static str {
+        "weather-file"
+    }
+
+    fn current_weather(&self, _client: &Client, _query: &Query) -> Result<Observation, WeatherError> {
+        Ok(self.observation.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weather::{Condition, WeatherError};
+
+    #[test]
+    fn can_read_a_saved_apixu_response() {
+        let obs = parse(include_str!("../fixtures/apixu/current.json")).unwrap();
+        assert_eq!(obs.place.name, "Marsala");
+        assert_eq!(obs.local_time, "2026-10-18 12:30");
+        assert_eq!(obs.temp_c, 18.0);
+        assert_eq!(obs.feelslike_c, 17.1);
+        assert_eq!(obs.humidity, Some(68));
+        assert_eq!(obs.condition, Condition::LightRain);
+        assert!(obs.is_day);
+    }
+
+    #[test]
+    fn can_read_the_toml_form() {
+        let obs = parse(r#"
+city = "Marsala"
+time = "2026-10-18 06:30"
+temp_c = 12
+wind_kph = 15.5
+condition = "drizzle"
+"#)
+            .unwrap();
+        assert_eq!(obs.place.name, "Marsala");
+        assert_eq!(obs.temp_c, 12.0);
+        assert_eq!(obs.feelslike_c, 12.0);
+        assert_eq!(obs.wind_kph, 15.5);
+        assert_eq!(obs.condition, Condition::Drizzle);
+        assert!(!obs.is_day);
+    }
+
+    #[test]
+    fn rejects_unknown_conditions() {
+        match parse("temp_c = 12.0\ncondition = \"sunny\"\n") {
+            Err(WeatherError::InvalidWeatherFile(e)) => assert_eq!(e, "condition: sunny"),
+            other => panic!("Expected an InvalidWeatherFile error, got {:?}", other),
+        }
+    }
+}
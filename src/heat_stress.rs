@@ -0,0 +1,106 @@
+//! How much the humidity weighs on a run in the heat, out of the dew point.
+
+use std::fmt;
+
+use weather::Observation;
+
+pub static HEAT_STRESS_BANDS: [&'static str; 6] = ["comfortable", "sticky", "uncomfortable",
+                                                   "oppressive", "dangerous", "extreme"];
+
+/// The bands runners commonly use for the dew point, from 13°C (55°F) up in
+/// steps of about 3°C (5°F).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HeatStress {
+    Comfortable,
+    Sticky,
+    Uncomfortable,
+    Oppressive,
+    Dangerous,
+    Extreme,
+}
+
+impl HeatStress {
+    pub fn from_dew_point(dew_point_c: f32) -> HeatStress {
+        if dew_point_c < 12.8 {
+            return HeatStress::Comfortable;
+        }
+        if dew_point_c < 15.6 {
+            return HeatStress::Sticky;
+        }
+        if dew_point_c < 18.3 {
+            return HeatStress::Uncomfortable;
+        }
+        if dew_point_c < 21.1 {
+            return HeatStress::Oppressive;
+        }
+        if dew_point_c < 23.9 {
+            return HeatStress::Dangerous;
+        }
+        HeatStress::Extreme
+    }
+
+    /// The heat stress of the given weather, unless we know neither the
+    /// humidity nor the dew point.
+    pub fn of(weather: &Observation) -> Option<HeatStress> {
+        weather.dew_point().map(HeatStress::from_dew_point)
+    }
+
+    /// The name used in the clothing rules, e.g. "oppressive".
+    pub fn name(&self) -> &'static str {
+        HEAT_STRESS_BANDS[*self as usize]
+    }
+
+    /// Whether it's worth warning the runner about.
+    pub fn is_warning(&self) -> bool {
+        *self >= HeatStress::Uncomfortable
+    }
+
+    pub fn advice(&self) -> &'static str {
+        match *self {
+            HeatStress::Comfortable => "Enjoy your run.",
+            HeatStress::Sticky => "A bit sticky, but you won't notice much.",
+            HeatStress::Uncomfortable => "Sweat won't evaporate well: expect to slow down a bit.",
+            HeatStress::Oppressive => {
+                "Hard efforts will suffer: ease the pace and drink before you're thirsty."
+            }
+            HeatStress::Dangerous => {
+                "Your body can't cool itself: run easy, carry water and know the signs of \
+                 heat exhaustion."
+            }
+            HeatStress::Extreme => {
+                "Heat illness is likely: run indoors, at dawn, or not at all today."
+            }
+        }
+    }
+}
+
+impl fmt::Display for HeatStress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HeatStress::Comfortable => write!(f, "Comfortable"),
+            HeatStress::Sticky => write!(f, "Sticky"),
+            HeatStress::Uncomfortable => write!(f, "Uncomfortable"),
+            HeatStress::Oppressive => write!(f, "Oppressive"),
+            HeatStress::Dangerous => write!(f, "Dangerous"),
+            HeatStress::Extreme => write!(f, "Extreme"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weather::Observation;
+
+    fn weather(temp_c: f32, humidity: u8) -> Observation {
+        Observation { temp_c: temp_c, humidity: Some(humidity), ..Default::default() }
+    }
+
+    #[test]
+    fn humidity_makes_the_same_heat_dangerous() {
+        assert_eq!(HeatStress::of(&weather(24.0, 30)), Some(HeatStress::Comfortable));
+        assert_eq!(HeatStress::of(&weather(24.0, 90)), Some(HeatStress::Dangerous));
+        assert_eq!(HeatStress::of(&Observation::default()), None);
+        assert_eq!(HeatStress::Dangerous.name(), "dangerous");
+    }
+}
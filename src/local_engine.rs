@@ -0,0 +1,582 @@
+//! Recommends what to wear without asking runnersworld.com, out of the
+//! same answers its form takes.
+
+extern crate toml;
+
+use std;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use config::{self, ConfigError};
+use heat_stress::{HeatStress, HEAT_STRESS_BANDS};
+use rain_gear::{RainGear, RAIN_GEAR};
+use recommendation::{BodyPart, Garment, Recommendation};
+use runners_world::FormBuilder;
+use uv_index::{UvBand, UV_BANDS};
+use weather::Observation;
+
+/// The rules viktor ships with, see `rules/default.toml`.
+static DEFAULT_RULES: &'static str = include_str!("../rules/default.toml");
+
+static KEYS: [&'static str; 13] = ["body_part", "name", "description", "min_temp", "max_temp",
+                                   "conditions", "wind", "time_of_day", "intensity", "gender",
+                                   "heat_stress", "rain", "uv"];
+static BODY_PARTS: [&'static str; 5] = ["head", "torso", "hands", "legs", "feet"];
+static CONDITIONS: [&'static str; 6] = ["c", "pc", "o", "lr", "r", "s"];
+static WINDS: [&'static str; 3] = ["nw", "lw", "hw"];
+static TIMES_OF_DAY: [&'static str; 4] = ["dawn", "day", "dusk", "night"];
+static INTENSITIES: [&'static str; 4] = ["n", "lr", "h", "r"];
+static GENDERS: [&'static str; 2] = ["m", "f"];
+
+/// A `[[rule]]` of a rules file, as written.
+#[derive(Deserialize, Debug, Default)]
+struct RuleEntry {
+    body_part: String,
+    name: String,
+    description: String,
+    min_temp: Option<i32>,
+    max_temp: Option<i32>,
+    #[serde(default)]
+    conditions: Vec<String>,
+    #[serde(default)]
+    wind: Vec<String>,
+    #[serde(default)]
+    time_of_day: Vec<String>,
+    #[serde(default)]
+    intensity: Vec<String>,
+    #[serde(default)]
+    gender: Vec<String>,
+    #[serde(default)]
+    heat_stress: Vec<String>,
+    #[serde(default)]
+    rain: Vec<String>,
+    #[serde(default)]
+    uv: Vec<String>,
+}
+
+/// When to suggest a garment. Temperatures are in fahrenheit (like the form
+/// buckets), the minimum inclusive and the maximum exclusive. An empty list
+/// of form answers matches any answer.
+#[derive(Debug, Clone)]
+struct Rule {
+    body_part: BodyPart,
+    name: String,
+    description: String,
+    min_temp: Option<i32>,
+    max_temp: Option<i32>,
+    conditions: Vec<String>,
+    wind: Vec<String>,
+    time_of_day: Vec<String>,
+    intensity: Vec<String>,
+    gender: Vec<String>,
+    heat_stress: Vec<String>,
+    rain: Vec<String>,
+    uv: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum RulesError {
+    IOError(std::io::Error),
+    /// The rules file is not valid, with the line of the offending rule.
+    InvalidRules(String),
+}
+
+impl From<std::io::Error> for RulesError {
+    fn from(err: std::io::Error) -> RulesError {
+        RulesError::IOError(err)
+    }
+}
+
+/// The clothing rules the local engine dresses by.
+#[derive(Debug, Clone)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// The rules viktor ships with.
+    pub fn bundled() -> Rules {
+        Rules::parse(DEFAULT_RULES).expect("The bundled clothing rules are invalid")
+    }
+
+    /// Reads the rules in the given file, or the bundled ones if there is none.
+    pub fn load(path: Option<&Path>) -> Result<Rules, RulesError> {
+        match path {
+            None => Ok(Rules::bundled()),
+            Some(p) => {
+                let mut contents = String::new();
+                let mut file = try!(File::open(p));
+                let _ = try!(file.read_to_string(&mut contents));
+                Rules::parse(&contents)
+            }
+        }
+    }
+
+    /// Parses and validates a rules file, reporting the line of the first
+    /// invalid rule.
+    pub fn parse(contents: &str) -> Result<Rules, RulesError> {
+        let table = try!(config::parse_toml(contents).map_err(|e| match e {
+            ConfigError::ParseError(desc) => RulesError::InvalidRules(desc),
+            other => RulesError::InvalidRules(format!("{:?}", other)),
+        }));
+        let entries = match table.get("rule").map(|r| r.as_slice()) {
+            Some(Some(entries)) => entries,
+            Some(None) => {
+                return Err(RulesError::InvalidRules("`rule` must be a list of [[rule]] tables"
+                    .to_owned()))
+            }
+            None => return Err(RulesError::InvalidRules("there is no [[rule]]".to_owned())),
+        };
+        let lines = rule_lines(contents);
+        let mut rules = Vec::new();
+        for (i, entry) in entries.iter().enumerate() {
+            let rule = try!(to_rule(entry).map_err(|e| {
+                let at = match lines.get(i) {
+                    Some(line) => format!("line {}", line),
+                    None => format!("rule {}", i + 1),
+                };
+                RulesError::InvalidRules(format!("{}: {}", at, e))
+            }));
+            rules.push(rule);
+        }
+        Ok(Rules { rules: rules })
+    }
+
+    /// The same rules, with the temperature thresholds of each body part
+    /// moved by the given offsets in fahrenheit: a positive one keeps the
+    /// warmer garments on up to higher temperatures.
+    pub fn tuned(&self, offsets: &BTreeMap<BodyPart, i32>) -> Rules {
+        let rules = self.rules
+            .iter()
+            .map(|rule| {
+                let offset = offsets.get(&rule.body_part).cloned().unwrap_or(0);
+                let mut tuned = rule.clone();
+                tuned.min_temp = rule.min_temp.map(|t| t + offset);
+                tuned.max_temp = rule.max_temp.map(|t| t + offset);
+                tuned
+            })
+            .collect();
+        Rules { rules: rules }
+    }
+}
+
+/// The (1-based) lines of the `[[rule]]` headers, in order. The TOML parser
+/// doesn't tell where the values come from, so this follows the syntax just
+/// enough: headers may have spaces (`[[ rule ]]`) or a comment, and
+/// multi-line strings may contain anything.
+fn rule_lines(contents: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut open_string: Option<&str> = None;
+    for (i, line) in contents.lines().enumerate() {
+        if open_string.is_none() && is_rule_header(line) {
+            lines.push(i + 1);
+            continue;
+        }
+        let mut rest = line;
+        loop {
+            let delimiter = match open_string {
+                Some(d) => rest.find(d).map(|at| (at, d)),
+                None => {
+                    vec!["\"\"\"", "'''"]
+                        .into_iter()
+                        .filter_map(|d| rest.find(d).map(|at| (at, d)))
+                        .min()
+                }
+            };
+            match delimiter {
+                Some((at, d)) => {
+                    rest = &rest[at + d.len()..];
+                    open_string = if open_string.is_some() { None } else { Some(d) };
+                }
+                None => break,
+            }
+        }
+    }
+    lines
+}
+
+fn is_rule_header(line: &str) -> bool {
+    let header: String = line.split('#')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    header == "[[rule]]"
+}
+
+fn check_answers(key: &str, values: &[String], allowed: &[&str]) -> Result<(), String> {
+    match values.iter().find(|v| !allowed.contains(&v.as_str())) {
+        Some(v) => Err(format!("unknown {} `{}`, expected one of {}", key, v, allowed.join(", "))),
+        None => Ok(()),
+    }
+}
+
+fn to_rule(entry: &toml::Value) -> Result<Rule, String> {
+    let table = match entry.as_table() {
+        Some(t) => t,
+        None => return Err(format!("expected a [[rule]] table, found a {}", entry.type_str())),
+    };
+    if let Some(key) = table.keys().find(|k| !KEYS.contains(&k.as_str())) {
+        return Err(format!("unknown key `{}`", key));
+    }
+    let raw: RuleEntry = try!(config::decode(table.clone()).map_err(|e| match e {
+        ConfigError::ParseError(desc) => desc,
+        other => format!("{:?}", other),
+    }));
+    let body_part = match BodyPart::parse(&raw.body_part) {
+        Some(b) => b,
+        None => {
+            return Err(format!("unknown body_part `{}`, expected one of {}",
+                               raw.body_part,
+                               BODY_PARTS.join(", ")))
+        }
+    };
+    try!(check_answers("condition", &raw.conditions, &CONDITIONS));
+    try!(check_answers("wind", &raw.wind, &WINDS));
+    try!(check_answers("time_of_day", &raw.time_of_day, &TIMES_OF_DAY));
+    try!(check_answers("intensity", &raw.intensity, &INTENSITIES));
+    try!(check_answers("gender", &raw.gender, &GENDERS));
+    try!(check_answers("heat_stress", &raw.heat_stress, &HEAT_STRESS_BANDS));
+    try!(check_answers("rain", &raw.rain, &RAIN_GEAR));
+    try!(check_answers("uv", &raw.uv, &UV_BANDS));
+    if let (Some(min), Some(max)) = (raw.min_temp, raw.max_temp) {
+        if min >= max {
+            return Err(format!("min_temp ({}) must be lower than max_temp ({})", min, max));
+        }
+    }
+    Ok(Rule {
+        body_part: body_part,
+        name: raw.name,
+        description: raw.description,
+        min_temp: raw.min_temp,
+        max_temp: raw.max_temp,
+        conditions: raw.conditions,
+        wind: raw.wind,
+        time_of_day: raw.time_of_day,
+        intensity: raw.intensity,
+        gender: raw.gender,
+        heat_stress: raw.heat_stress,
+        rain: raw.rain,
+        uv: raw.uv,
+    })
+}
+
+/// The temperature to dress for, in fahrenheit: the form bucket, adjusted
+/// for how much the run (and the weather) will warm you up.
+pub fn effective_temperature(form: &FormBuilder) -> i32 {
+    let bucket = if form.temperature == "zero" {
+        0
+    } else {
+        form.temperature.parse().unwrap_or(50)
+    };
+    let intensity = match form.intensity {
+        "h" => 5,
+        "r" => 10,
+        _ => 0,
+    };
+    let feel = match form.feel {
+        "c" => -5,
+        "w" => 5,
+        _ => 0,
+    };
+    let wind = if form.wind == "hw" { -5 } else { 0 };
+    let time_of_day = match form.time_of_day {
+        "night" | "dawn" => -5,
+        _ => 0,
+    };
+    bucket + intensity + feel + wind + time_of_day
+}
+
+fn answers(allowed: &[String], answer: &str) -> bool {
+    allowed.is_empty() || allowed.iter().any(|a| a == answer)
+}
+
+/// How much warmer the humidity makes it feel, as sweat can't evaporate.
+fn heat_stress_adjustment(heat_stress: Option<HeatStress>) -> i32 {
+    match heat_stress {
+        Some(HeatStress::Oppressive) => 5,
+        Some(HeatStress::Dangerous) | Some(HeatStress::Extreme) => 10,
+        _ => 0,
+    }
+}
+
+/// What the rules are matched against: the form answers, and what the
+/// weather tells beyond them.
+struct Situation<'a> {
+    form: &'a FormBuilder,
+    /// The effective temperature, in fahrenheit
+    temp: i32,
+    /// Empty if unknown
+    heat_stress: &'static str,
+    rain: &'static str,
+    /// Empty if unknown
+    uv: &'static str,
+}
+
+impl<'a> Situation<'a> {
+    fn new(form: &'a FormBuilder, weather: &Observation) -> Situation<'a> {
+        let heat_stress = HeatStress::of(weather);
+        Situation {
+            form: form,
+            temp: effective_temperature(form) + heat_stress_adjustment(heat_stress),
+            heat_stress: heat_stress.map(|h| h.name()).unwrap_or(""),
+            rain: RainGear::of(weather).name(),
+            uv: UvBand::of(weather).map(|u| u.name()).unwrap_or(""),
+        }
+    }
+}
+
+fn applies(rule: &Rule, s: &Situation) -> bool {
+    rule.min_temp.map(|t| s.temp >= t).unwrap_or(true) &&
+    rule.max_temp.map(|t| s.temp < t).unwrap_or(true) &&
+    answers(&rule.conditions, s.form.conditions) && answers(&rule.wind, s.form.wind) &&
+    answers(&rule.time_of_day, s.form.time_of_day) &&
+    answers(&rule.intensity, s.form.intensity) && answers(&rule.gender, s.form.gender) &&
+    answers(&rule.heat_stress, s.heat_stress) && answers(&rule.rain, s.rain) &&
+    answers(&rule.uv, s.uv)
+}
+
+/// What to wear, out of the form answers and what the weather tells beyond
+/// them (e.g. the humidity, or how much it rains). A garment suggested by
+/// more than one rule is worn once, as described by the first one.
+pub fn recommend(rules: &Rules, form: &FormBuilder, weather: &Observation) -> Recommendation {
+    let situation = Situation::new(form, weather);
+    let mut items: Vec<Garment> = Vec::new();
+    for rule in rules.rules.iter().filter(|r| applies(r, &situation)) {
+        if items.iter().any(|g| g.body_part == rule.body_part && g.name == rule.name) {
+            continue;
+        }
+        items.push(Garment {
+            body_part: rule.body_part,
+            name: rule.name.clone(),
+            description: rule.description.clone(),
+            image: None,
+        });
+    }
+    Recommendation::new(items)
+}
+
+/// What to wear for a run which warms up from `weather` (answered with
+/// `form`) to `warm_weather` (answered with `warm_form`): dressed for the
+/// cold start, telling which garments are layers to take off on the way
+/// (hats, gloves and jackets: nobody takes their tights off mid-run).
+pub fn recommend_layers(rules: &Rules,
+                        form: &FormBuilder,
+                        weather: &Observation,
+                        warm_form: &FormBuilder,
+                        warm_weather: &Observation)
+                        -> Recommendation {
+    let warm = recommend(rules, warm_form, warm_weather);
+    let items = recommend(rules, form, weather)
+        .items
+        .into_iter()
+        .map(|mut g| {
+            let removable = match g.body_part {
+                BodyPart::Head | BodyPart::Torso | BodyPart::Hands => true,
+                BodyPart::Legs | BodyPart::Feet => false,
+            };
+            if removable &&
+               !warm.items.iter().any(|w| w.body_part == g.body_part && w.name == g.name) {
+                g.description = format!("{} Take it off as it warms up to {:.0}C.",
+                                        g.description,
+                                        warm_weather.temp_c);
+            }
+            g
+        })
+        .collect();
+    Recommendation::new(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use recommendation::BodyPart;
+    use runners_world::FormBuilder;
+    use weather::{Condition, Observation};
+
+    fn form(gender: &'static str,
+            temperature: &'static str,
+            conditions: &'static str,
+            time_of_day: &'static str)
+            -> FormBuilder {
+        FormBuilder {
+            gender: gender,
+            temperature: temperature,
+            conditions: conditions,
+            wind: "lw",
+            time_of_day: time_of_day,
+            intensity: "n",
+            feel: "ib",
+            explanations: Vec::new(),
+        }
+    }
+
+    fn names_in(form: &FormBuilder, weather: &Observation) -> Vec<String> {
+        recommend(&Rules::bundled(), form, weather).items.into_iter().map(|g| g.name).collect()
+    }
+
+    fn names(form: &FormBuilder) -> Vec<String> {
+        names_in(form, &Observation::default())
+    }
+
+    #[test]
+    fn dresses_for_the_cold() {
+        let items = names(&form("m", "15", "o", "day"));
+        assert_eq!(items,
+                   vec!["Warm hat", "Thermal base layer", "Insulated jacket", "Mittens",
+                        "Wind briefs", "Thermal tights", "Wool socks"]);
+    }
+
+    #[test]
+    fn dresses_for_a_sunny_summer_day() {
+        let items = names(&form("f", "75", "c", "day"));
+        assert_eq!(items,
+                   vec!["Visor", "Sunglasses", "Sports bra", "Singlet", "Shorts", "Running socks"]);
+    }
+
+    #[test]
+    fn harder_efforts_feel_warmer() {
+        let drizzle = Observation { condition: Condition::Drizzle, ..Default::default() };
+        let mut easy = form("m", "40", "lr", "day");
+        assert!(names_in(&easy, &drizzle).contains(&"Capris".to_owned()));
+        easy.intensity = "r";
+        assert!(names_in(&easy, &drizzle).contains(&"Shorts".to_owned()));
+        assert!(names_in(&easy, &drizzle).contains(&"Rain shell".to_owned()));
+    }
+
+    #[test]
+    fn downpours_call_for_waterproofs_and_dry_socks() {
+        let downpour = Observation {
+            condition: Condition::Rain,
+            precip_mm: Some(6.5),
+            ..Default::default()
+        };
+        let items = names_in(&form("f", "50", "r", "day"), &downpour);
+        assert!(items.contains(&"Waterproof jacket".to_owned()));
+        assert!(items.contains(&"Cap".to_owned()));
+        assert!(items.contains(&"Spare socks".to_owned()));
+        assert!(!items.contains(&"Rain shell".to_owned()));
+    }
+
+    #[test]
+    fn humid_days_call_for_less_and_lighter_clothes() {
+        let humid = Observation { temp_c: 24.0, humidity: Some(90), ..Default::default() };
+        let items = names_in(&form("m", "60", "o", "day"), &humid);
+        assert!(items.contains(&"Singlet".to_owned()));
+        assert!(items.contains(&"Vented cap".to_owned()));
+        assert!(!names(&form("m", "60", "o", "day")).contains(&"Singlet".to_owned()));
+    }
+
+    #[test]
+    fn protects_lunch_runners_from_the_sun() {
+        let spring_noon = Observation { uv_index: Some(7.0), ..Default::default() };
+        let items = names_in(&form("m", "60", "pc", "day"), &spring_noon);
+        assert!(items.contains(&"Sunscreen".to_owned()));
+        assert!(items.contains(&"Sun cap".to_owned()));
+        assert_eq!(items.iter().filter(|n| *n == "Sunglasses").count(), 1);
+        assert!(!names(&form("m", "60", "pc", "day")).contains(&"Sunscreen".to_owned()));
+    }
+
+    #[test]
+    fn club_rules_can_move_the_thresholds() {
+        let rules = Rules::parse(r#"
+[[rule]]
+body_part = "legs"
+name = "Shorts"
+description = "Real runners wear shorts."
+min_temp = 30
+"#)
+            .unwrap();
+        let items = recommend(&rules, &form("m", "30", "o", "day"), &Observation::default()).items;
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].body_part, BodyPart::Legs);
+        assert_eq!(items[0].name, "Shorts");
+    }
+
+    #[test]
+    fn feedback_moves_the_thresholds_of_a_body_part() {
+        let mut offsets = BTreeMap::new();
+        offsets.insert(BodyPart::Hands, 4);
+        let rules = Rules::bundled().tuned(&offsets);
+        let chilly = form("m", "45", "o", "day");
+        let items: Vec<String> = recommend(&rules, &chilly, &Observation::default())
+            .items
+            .into_iter()
+            .map(|g| g.name)
+            .collect();
+        assert!(items.contains(&"Gloves".to_owned()));
+        assert!(items.contains(&"Capris".to_owned()));
+        assert!(!names(&chilly).contains(&"Gloves".to_owned()));
+    }
+
+    #[test]
+    fn runs_warming_up_call_for_layers_to_take_off() {
+        let cold = Observation { temp_c: 4.0, ..Default::default() };
+        let warm = Observation { temp_c: 14.0, ..Default::default() };
+        let steady = Observation { temp_c: 9.0, ..Default::default() };
+        let layered = recommend_layers(&Rules::bundled(),
+                                       &form("m", "30", "o", "day"),
+                                       &cold,
+                                       &form("m", "50", "o", "day"),
+                                       &warm)
+            .items;
+        let steady = names_in(&form("m", "40", "o", "day"), &steady);
+        let jacket = layered.iter().find(|g| g.name == "Jacket").unwrap();
+        assert!(jacket.description.ends_with("Take it off as it warms up to 14C."));
+        let tights = layered.iter().find(|g| g.name == "Tights").unwrap();
+        assert_eq!(tights.description, "Running tights.");
+        assert!(!steady.contains(&"Jacket".to_owned()));
+        assert!(steady.contains(&"Capris".to_owned()));
+    }
+
+    #[test]
+    fn reports_the_line_of_invalid_rules() {
+        let contents = r#"
+[[rule]]
+body_part = "legs"
+name = "Shorts"
+description = "Running shorts."
+
+[[rule]]
+body_part = "legs"
+name = "Tights"
+description = "Running tights."
+min_temp = 40
+max_temp = 20
+"#;
+        match Rules::parse(contents) {
+            Err(RulesError::InvalidRules(e)) => {
+                assert_eq!(e, "line 7: min_temp (40) must be lower than max_temp (20)")
+            }
+            other => panic!("Expected an InvalidRules error, got {:?}", other),
+        }
+        let contents = r#"
+[[rule]]
+body_part = "legs"
+name = "Shorts"
+description = """
+Running shorts.
+[[rule]]
+"""
+
+[[ rule ]] # Too warm for tights
+body_part = "legs"
+name = "Tights"
+description = "Running tights."
+min_temp = 40
+max_temp = 20
+"#;
+        match Rules::parse(contents) {
+            Err(RulesError::InvalidRules(e)) => assert!(e.starts_with("line 10: ")),
+            other => panic!("Expected an InvalidRules error, got {:?}", other),
+        }
+        match Rules::parse("[[rule]]\nbody_part = \"neck\"\nname = \"Scarf\"\ndescription = \"\"\n") {
+            Err(RulesError::InvalidRules(e)) => assert!(e.starts_with("line 1: unknown body_part")),
+            other => panic!("Expected an InvalidRules error, got {:?}", other),
+        }
+    }
+}
@@ -4,6 +4,7 @@
 extern crate hyper;
 extern crate select;
 extern crate termcolor;
+extern crate time;
 
 #[macro_use] extern crate serde_derive;
 #[macro_use] extern crate serde;
@@ -11,35 +12,98 @@ extern crate termcolor;
 
 use hyper::client::Client;
 
-use std::io::Read;
+use std::fs::File;
+use std::path::Path;
 use std::process;
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 
 use select::document::Document;
-use select::predicate::{Predicate, Attr, Name};
-use std::iter::FromIterator;
-use std::iter::IntoIterator;
 
 use std::io::Write;
 use termcolor::{Color, ColorChoice, ColorSpec, Stdout, WriteColor};
 
 mod apixu_weather;
+mod cache;
+mod config;
+mod elevation;
+mod feedback;
+mod file_weather;
+mod heat_stress;
+mod local_engine;
 mod runners_world;
+mod safety;
 mod cli;
+mod met_norway_weather;
+mod metar_weather;
+mod nws_weather;
+mod open_meteo_weather;
+mod openweathermap_weather;
+mod rain_gear;
+mod recommendation;
+mod run_window;
+mod uv_index;
+mod weather;
+
+#[cfg(test)]
+mod test_server;
 
 #[derive(Debug)]
 enum AppError {
     CliError(cli::CliParseError),
+    ConfigError(config::ConfigError),
+    RulesError(local_engine::RulesError),
+    RouteError(elevation::RouteError),
+    FeedbackError(feedback::FeedbackError),
+    ScrapeError(runners_world::ScrapeError),
+    UnknownEngine(std::string::String),
     GenericError(std::string::String)
 }
 
-impl From<apixu_weather::ApixuError> for AppError {
-    fn from(err: apixu_weather::ApixuError) -> AppError {
+static DEFAULT_ENGINE: &'static str = "local";
+static ENGINES: [&'static str; 2] = ["local", "runners-world"];
+
+impl From<weather::WeatherError> for AppError {
+    fn from(err: weather::WeatherError) -> AppError {
         AppError::GenericError(format!("{:?}", err))
     }
 }
 
-fn show_visual_recap(args: &cli::Args, weather: &apixu_weather::CurrentWeather) -> Result<(), Box<::std::error::Error>>{
+impl From<config::ConfigError> for AppError {
+    fn from(err: config::ConfigError) -> AppError {
+        AppError::ConfigError(err)
+    }
+}
+
+impl From<local_engine::RulesError> for AppError {
+    fn from(err: local_engine::RulesError) -> AppError {
+        AppError::RulesError(err)
+    }
+}
+
+impl From<elevation::RouteError> for AppError {
+    fn from(err: elevation::RouteError) -> AppError {
+        AppError::RouteError(err)
+    }
+}
+
+impl From<feedback::FeedbackError> for AppError {
+    fn from(err: feedback::FeedbackError) -> AppError {
+        AppError::FeedbackError(err)
+    }
+}
+
+impl From<runners_world::ScrapeError> for AppError {
+    fn from(err: runners_world::ScrapeError) -> AppError {
+        AppError::ScrapeError(err)
+    }
+}
+
+fn show_visual_recap(args: &cli::Args,
+                     weather: &weather::Observation,
+                     window: &Option<run_window::RunWindow>,
+                     station_elevation: Option<f32>,
+                     thermal_offset: f32)
+                     -> Result<(), Box<::std::error::Error>> {
     let mut stdout = Stdout::new(ColorChoice::Always);
     println!("\n");
 
@@ -59,59 +123,259 @@ fn show_visual_recap(args: &cli::Args, weather: &apixu_weather::CurrentWeather)
     try!(write!(&mut stdout, "City: "));
     try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green))));
     let city = args.city.clone();
-    let city_name = weather.location.name.clone();
-    let country   = weather.location.country.clone();
-    try!(writeln!(&mut stdout, "{}", city.unwrap_or(city_name + ", " + &country.to_string() + " (Inferred)")));
+    let city_name = weather.place.name.clone();
+    let country   = weather.place.country.clone();
+    let inferred  = if country.is_empty() { city_name } else { city_name + ", " + &country };
+    try!(writeln!(&mut stdout, "{}", city.unwrap_or(inferred + " (Inferred)")));
     try!(stdout.reset());
 
+    // Elevation
+    if let Some(elevation) = weather.elevation_m {
+        try!(write!(&mut stdout, "Elevation: "));
+        try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green))));
+        try!(write!(&mut stdout, "{:.0}m", elevation));
+        try!(stdout.reset());
+        match station_elevation {
+            Some(s) if s != elevation => {
+                try!(writeln!(&mut stdout, " (the weather station is at {:.0}m)", s))
+            }
+            _ => try!(writeln!(&mut stdout, "")),
+        }
+    }
+
     // Temp
-    try!(write!(&mut stdout, "Temperature now: "));
+    let when = if window.is_some() {
+        "over the run"
+    } else if args.start.is_some() {
+        "at start"
+    } else {
+        "now"
+    };
+    try!(write!(&mut stdout, "Temperature {}: ", when));
     try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan))));
-    let temp_c = weather.current.temp_c;
-    let temp_f = weather.current.temp_f;
-    try!(writeln!(&mut stdout, "{}C ({}F)", temp_c, temp_f));
+    let temp_c = weather.temp_c;
+    let temp_f = weather.temp_f();
+    match *window {
+        // The weather is the coldest of the run, which is what it dresses for.
+        Some(ref w) => {
+            let max_c = w.max_temp_c();
+            try!(writeln!(&mut stdout,
+                          "{}C to {}C ({}F to {}F)",
+                          temp_c,
+                          max_c,
+                          temp_f,
+                          weather::celsius_to_fahrenheit(max_c)))
+        }
+        None => try!(writeln!(&mut stdout, "{}C ({}F)", temp_c, temp_f)),
+    }
     try!(stdout.reset());
 
+    // Effective temperature
+    if thermal_offset != 0.0 {
+        try!(write!(&mut stdout, "Dressing for: "));
+        try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan))));
+        let effective_c = temp_c + thermal_offset;
+        let effective_f = temp_f + thermal_offset * 9.0 / 5.0;
+        try!(writeln!(&mut stdout,
+                      "{:.1}C ({:.1}F), as you run {} by {}C",
+                      effective_c,
+                      effective_f,
+                      if thermal_offset > 0.0 { "hot" } else { "cold" },
+                      thermal_offset.abs()));
+        try!(stdout.reset());
+    }
+
     // Weather
-    try!(write!(&mut stdout, "Weather now: "));
+    try!(write!(&mut stdout, "Weather {}: ", when));
     try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan))));
-    let condition = &weather.current.condition.text;
+    let condition = &weather.condition_text;
     try!(writeln!(&mut stdout, "{}", condition));
     try!(stdout.reset());
 
     // Wind
     try!(write!(&mut stdout, "Wind: "));
     try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan))));
-    let wind_kph = &weather.current.wind_kph;
-    let wind_mph = &weather.current.wind_mph;
+    let wind_kph = weather.wind_kph;
+    let wind_mph = weather.wind_mph();
     try!(writeln!(&mut stdout, "{} kph ({} mph)", wind_kph, wind_mph));
     try!(stdout.reset());
 
+    // Rain
+    if weather.precip_mm.is_some() || weather.precip_chance.is_some() {
+        try!(write!(&mut stdout, "Rain: "));
+        try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan))));
+        let amount = weather.precip_mm.map(|p| format!("{:.1} mm", p));
+        let chance = weather.precip_chance.map(|c| format!("{}% chance", c));
+        let known: Vec<String> = amount.into_iter().chain(chance).collect();
+        try!(writeln!(&mut stdout,
+                      "{} ({})",
+                      known.join(", "),
+                      rain_gear::RainGear::of(weather)));
+        try!(stdout.reset());
+    }
+
+    // UV index
+    if let (Some(index), Some(band)) = (weather.uv_index, uv_index::UvBand::of(weather)) {
+        try!(write!(&mut stdout, "UV index: "));
+        let color = match band {
+            uv_index::UvBand::Low => Color::Green,
+            uv_index::UvBand::Moderate | uv_index::UvBand::High => Color::Yellow,
+            uv_index::UvBand::VeryHigh => Color::Red,
+            uv_index::UvBand::Extreme => Color::Magenta,
+        };
+        try!(stdout.set_color(ColorSpec::new()
+            .set_fg(Some(color))
+            .set_bold(band >= uv_index::UvBand::High)));
+        try!(write!(&mut stdout, "{:.0} ({})", index, band));
+        try!(stdout.reset());
+        try!(writeln!(&mut stdout, " {}", band.advice()));
+    }
+
+    // Run window
+    if let Some(ref w) = *window {
+        try!(write!(&mut stdout, "Over the run ({} min): ", w.duration_minutes));
+        try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Cyan))));
+        try!(writeln!(&mut stdout,
+                      "wind up to {:.1} kph{}",
+                      w.max_wind_kph(),
+                      if w.is_wet() { ", wet" } else { "" }));
+        try!(stdout.reset());
+    }
+
 
     // Finalise everything
     println!("\n");
     Ok(())
 }
 
-fn print_descriptions(descriptions: HashSet<(String, String)>) -> Result<(), Box<::std::error::Error>> {
+/// How each answer of the form was chosen, one per line.
+fn show_explanation(explanations: &[runners_world::Explanation]) {
+    println!("How the weather was read:");
+    for e in explanations {
+        println!("  {:<12} {} -> {} ({})", e.field, e.input, e.bucket, e.rule);
+    }
+    println!("");
+}
+
+/// Warns about running in the humid heat, if it's worth it.
+fn show_heat_stress(weather: &weather::Observation) -> Result<(), Box<::std::error::Error>> {
+    let heat_stress = match heat_stress::HeatStress::of(weather) {
+        Some(h) if h.is_warning() => h,
+        _ => return Ok(()),
+    };
+    let mut stdout = Stdout::new(ColorChoice::Always);
+    let color = if heat_stress >= heat_stress::HeatStress::Dangerous {
+        Color::Red
+    } else {
+        Color::Yellow
+    };
+    try!(stdout.set_color(ColorSpec::new().set_fg(Some(color)).set_bold(true)));
+    try!(write!(&mut stdout, "Heat stress: {}", heat_stress));
+    try!(stdout.reset());
+    let dew_point = weather.dew_point().unwrap_or(weather.temp_c);
+    try!(write!(&mut stdout,
+                " (dew point {:.1}C / {:.1}F",
+                dew_point,
+                weather::celsius_to_fahrenheit(dew_point)));
+    if let Some(humidity) = weather.relative_humidity() {
+        try!(write!(&mut stdout, ", humidity {:.0}%", humidity));
+    }
+    try!(writeln!(&mut stdout, ")"));
+    try!(stdout.set_color(ColorSpec::new().set_fg(Some(color))));
+    try!(writeln!(&mut stdout, "{}", heat_stress.advice()));
+    try!(stdout.reset());
+
+    Ok(())
+}
 
+fn print_recommendation(recommendation: &recommendation::Recommendation) -> Result<(), Box<::std::error::Error>> {
     let mut stdout = Stdout::new(ColorChoice::Always);
-    println!("");
-    for (item, desc) in descriptions {
+    let mut last_part = None;
+    for garment in recommendation.items.iter() {
+        if last_part != Some(garment.body_part) {
+            try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green))));
+            try!(writeln!(&mut stdout, "\n{}", garment.body_part));
+            try!(stdout.reset());
+            last_part = Some(garment.body_part);
+        }
         try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Yellow))));
-        try!(writeln!(&mut stdout, "{}", item));
+        try!(write!(&mut stdout, "{}: ", garment.name));
         try!(stdout.reset());
         try!(stdout.set_color(ColorSpec::new().set_bold(true)));
-        try!(writeln!(&mut stdout, "{}", desc));
+        try!(writeln!(&mut stdout, "{}", garment.description));
         try!(stdout.reset());
     }
 
     Ok(())
 }
 
+/// The gear to be seen with, in a section of its own, if it's dark or foggy.
+fn show_safety(hazards: &[safety::Hazard]) -> Result<(), Box<::std::error::Error>> {
+    if hazards.is_empty() {
+        return Ok(());
+    }
+    let mut stdout = Stdout::new(ColorChoice::Always);
+    let reasons: Vec<String> = hazards.iter().map(|h| h.to_string()).collect();
+    try!(stdout.set_color(ColorSpec::new().set_fg(Some(Color::Red)).set_bold(true)));
+    try!(writeln!(&mut stdout, "\nSafety: {}, make sure you are seen", reasons.join(" and ")));
+    try!(stdout.reset());
+    print_recommendation(&safety::safety_gear(hazards))
+}
+
+/// How the feedback moved the thresholds, e.g. "hands 4F warmer, legs 2F cooler".
+fn describe_offsets(offsets: &BTreeMap<recommendation::BodyPart, i32>) -> String {
+    offsets.iter()
+        .map(|(part, offset)| {
+            format!("{} {}F {}",
+                    part.name(),
+                    offset.abs(),
+                    if *offset > 0 { "warmer" } else { "cooler" })
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Records how the last run felt, and tells what viktor learnt from it.
+fn give_feedback(args: cli::FeedbackArgs) -> Result<(), AppError> {
+    let dir = try!(config::data_dir()
+        .ok_or(AppError::GenericError("Couldn't find where to keep the feedback".to_owned())));
+    let history = try!(feedback::give(&dir, args.comfort, args.body_part));
+    let run = &history[history.len() - 1].run;
+    let offsets = feedback::offsets(&history, run.effective_temp_f);
+
+    let mut stdout = Stdout::new(ColorChoice::Always);
+    let _ = writeln!(&mut stdout,
+                     "Noted: {}{} at {:.1}C ({}).",
+                     args.comfort.name(),
+                     args.body_part.map(|b| format!(" ({})", b.name())).unwrap_or(String::new()),
+                     run.temp_c,
+                     run.local_time);
+    let _ = stdout.set_color(ColorSpec::new().set_fg(Some(Color::Green)));
+    if offsets.is_empty() {
+        let _ = writeln!(&mut stdout, "viktor dresses you as the clothing rules say.");
+    } else {
+        let _ = writeln!(&mut stdout,
+                         "From now on, around {}F, viktor dresses your {}.",
+                         run.effective_temp_f,
+                         describe_offsets(&offsets));
+    }
+    let _ = stdout.reset();
+    Ok(())
+}
+
 fn main() {
-   match cli::Args::parse().map_err(AppError::CliError).and_then(run) {
+   let result = cli::Command::parse().map_err(AppError::CliError).and_then(|command| {
+       match command {
+           cli::Command::Recommend(args) => run(args),
+           cli::Command::Feedback(args) => give_feedback(args),
+       }
+   });
+   match result {
         Ok(()) => process::exit(0),
+        Err(AppError::ScrapeError(e)) => {
+            let _ = writeln!(&mut std::io::stderr(), "{}", e);
+            process::exit(1);
+        }
         Err(e) => {
             println!("{:?}", e);
             process::exit(1);
@@ -119,40 +383,214 @@ fn main() {
    }
 }
 
+/// Where the weather comes from: a local report if the user gave us one,
+/// otherwise the configured provider.
+fn weather_source(args: &cli::Args, settings: &config::Settings) -> Result<Box<weather::WeatherProvider>, AppError> {
+    if let Some(ref report) = args.metar {
+        return Ok(Box::new(try!(metar_weather::MetarSource::from_report(report))));
+    }
+    if let Some(ref path) = args.metar_file {
+        return Ok(Box::new(try!(metar_weather::MetarSource::from_file(Path::new(path)))));
+    }
+    if let Some(ref path) = args.weather_file {
+        return Ok(Box::new(try!(file_weather::WeatherFile::open(Path::new(path)))));
+    }
+    let provider_name = args.provider.clone()
+        .or(settings.provider.clone())
+        .unwrap_or(weather::DEFAULT_PROVIDER.to_owned());
+    Ok(try!(weather::provider(&provider_name, settings)))
+}
+
+/// How high the user runs, in meters, if they told us.
+fn run_elevation(args: &cli::Args) -> Result<Option<f32>, AppError> {
+    match args.route {
+        Some(ref path) => Ok(Some(try!(elevation::route_summit(Path::new(path))))),
+        None => Ok(args.elevation),
+    }
+}
+
+/// The weather to dress for: the one described by the override flags alone,
+/// or the one fetched from the weather source (at the planned start, or over
+/// the whole run, if any) with the overrides applied. Either is moved to the
+/// elevation of the run. Along with it, the elevation of the weather station.
+fn observe(client: &Client,
+           args: &cli::Args,
+           settings: &config::Settings,
+           target_elevation: Option<f32>)
+           -> Result<(weather::Observation, Option<run_window::RunWindow>, Option<f32>), AppError> {
+    let treeline = settings.treeline_m.unwrap_or(elevation::DEFAULT_TREELINE_M);
+    let given_station = args.station_elevation.or(settings.station_elevation_m);
+    let adjust = |obs: weather::Observation| -> Result<weather::Observation, AppError> {
+        match target_elevation {
+            Some(target) => {
+                let station = try!(obs.elevation_m.or(given_station).ok_or(AppError::GenericError(
+                    String::from("The elevation of the weather station is unknown: give it with \
+                                  --station-elevation (or station_elevation_m in the config file)."))));
+                Ok(elevation::at_elevation(&obs, station, target, treeline))
+            }
+            None => Ok(obs),
+        }
+    };
+    if !args.has_weather_source(settings) {
+        let mut overrides = args.overrides.clone();
+        let now = weather::Place::default().local_now();
+        let start = args.start.as_ref().map(|s| s.resolve(&now));
+        overrides.local_time = overrides.local_time.or(start);
+        if let Some(obs) = overrides.synthetic() {
+            return Ok((try!(adjust(obs)), None, given_station));
+        }
+    }
+    let provider = try!(weather_source(args, settings));
+    let query = weather::Query::new(&args.city, &args.coords);
+    let (fetched, window, station) = match (&args.start, args.duration) {
+        (&None, None) => {
+            let current = try!(provider.current_weather(client, &query));
+            let station = current.elevation_m.or(given_station);
+            (try!(adjust(current)), None, station)
+        }
+        (&Some(ref start), None) => {
+            let forecast = try!(provider.hourly_forecast(client, &query));
+            let station = forecast.first().and_then(|o| o.elevation_m).or(given_station);
+            let start = start.resolve(&place_now(&forecast));
+            let forecast: Vec<_> = try!(forecast.into_iter().map(&adjust).collect::<Result<_, _>>());
+            (try!(weather::forecast_at(&forecast, &start)), None, station)
+        }
+        (start, Some(duration)) => {
+            let forecast = try!(provider.hourly_forecast(client, &query));
+            let station = forecast.first().and_then(|o| o.elevation_m).or(given_station);
+            let now = place_now(&forecast);
+            let start = start.as_ref().map(|s| s.resolve(&now)).unwrap_or(now);
+            let forecast: Vec<_> = try!(forecast.into_iter().map(&adjust).collect::<Result<_, _>>());
+            let window = try!(run_window::RunWindow::new(&forecast, &start, duration));
+            (window.to_observation(), Some(window), station)
+        }
+    };
+    Ok((args.overrides.apply(&fetched), window, station))
+}
+
+/// The local time now where the forecast is, which the planned start is
+/// relative to (rather than to the machine's clock).
+fn place_now(forecast: &[weather::Observation]) -> String {
+    forecast.first().map(|o| o.place.clone()).unwrap_or(weather::Place::default()).local_now()
+}
+
 fn run(args: cli::Args) -> Result<(), AppError> {
     let client = Client::new();
-    let mut body = String::new();
 
-    let weather = try!(apixu_weather::current_weather(&client, &args.city));
-    let form_builder = runners_world::FormBuilder::new(&args.gender, &args.intensity, &weather);
-    let url = form_builder.to_url().to_string();
-
-    let _ = client.get(&url)
-        .send()
-        .map(|mut r| r.read_to_string(&mut body))
-        .expect("Couldn't contact Runner's World website.");
-    let document = Document::from(body.as_str());
+    let cfg = try!(config::load());
+    let settings = try!(cfg.settings(&args.profile));
+    let engine = args.engine.clone()
+        .or(settings.engine.clone())
+        .unwrap_or(DEFAULT_ENGINE.to_owned());
+    if !ENGINES.contains(&engine.as_str()) {
+        return Err(AppError::UnknownEngine(engine));
+    }
+    let target_elevation = try!(run_elevation(&args));
+    let (weather, window, station_elevation) =
+        try!(observe(&client, &args, &settings, target_elevation));
+    let thermal_offset = args.thermal_offset.or(settings.thermal_offset).unwrap_or(0.0);
+    let form_builder = runners_world::FormBuilder::new(&args.gender,
+                                                       &args.intensity,
+                                                       &weather,
+                                                       thermal_offset);
 
     // Show a visual recap
-    let _ = show_visual_recap(&args, &weather);
-
-    for node in document.find(Attr("id", "content")) {
-        let pred = Name("table").descendant(Name("table").descendant(Name("td")));
-        let tds:Vec<_> = node.find(pred).collect();
-
-        let images = runners_world::find_images(&tds);
-        let mut images_sorted = Vec::from_iter(images);
-        images_sorted.sort();
-        for img_info in images_sorted {
-            runners_world::display_inline_image(&img_info,
-                                                runners_world::download_img(&client, img_info.url)
-                                                .map(|x| runners_world::to_base_64(&x))
-            );
-        }
+    let _ = show_visual_recap(&args, &weather, &window, station_elevation, thermal_offset);
+    let _ = show_heat_stress(&weather);
+    if args.explain {
+        show_explanation(&form_builder.explanations);
+    }
 
-        let descriptions = runners_world::find_descriptions(&tds);
-        let _ = print_descriptions(descriptions);
+    let data_dir = config::data_dir();
+    let effective_temp_f = local_engine::effective_temperature(&form_builder);
+    let recommendation = if engine == "runners-world" {
+        let cache_hours = settings.runners_world_cache_hours
+            .unwrap_or(runners_world::DEFAULT_CACHE_HOURS);
+        let cache = runners_world::PageCache::new(cache_hours);
+        let recommendation =
+            try!(ask_runners_world(&client, &cache, &form_builder, &args.dump_html));
+        show_pictures(&client, &recommendation);
+        recommendation
+    } else {
+        let rules_path = args.rules.clone().or(settings.rules.clone());
+        let rules = try!(local_engine::Rules::load(rules_path.as_ref().map(Path::new)));
+        let offsets = feedback::offsets(&data_dir.as_ref()
+                                            .map(|d| feedback::history(d))
+                                            .unwrap_or(Vec::new()),
+                                        effective_temp_f);
+        if !offsets.is_empty() {
+            println!("Tuned by your feedback: {}.", describe_offsets(&offsets));
+        }
+        let rules = rules.tuned(&offsets);
+        match window {
+            Some(ref w) => {
+                let warm = args.overrides.apply(&w.warmest_observation());
+                let warm_form = runners_world::FormBuilder::new(&args.gender,
+                                                                &args.intensity,
+                                                                &warm,
+                                                                thermal_offset);
+                local_engine::recommend_layers(&rules, &form_builder, &weather, &warm_form, &warm)
+            }
+            None => local_engine::recommend(&rules, &form_builder, &weather),
+        }
+    };
+    let _ = print_recommendation(&recommendation);
+    if let Some(ref d) = data_dir {
+        // Remembering the run is best effort, it's only needed for the feedback.
+        let last_run = feedback::LastRun::new(&weather, effective_temp_f, &recommendation);
+        let _ = feedback::record_run(d, &last_run);
     }
+    let _ = show_safety(&safety::hazards(&weather, form_builder.time_of_day));
 
     Ok(())
 }
+
+/// Asks runnersworld.com what to wear, unless the same page was asked for
+/// recently. If the page can't be understood, it is saved to `dump_html` (if
+/// given) to find out what changed.
+fn ask_runners_world(client: &Client,
+                     cache: &runners_world::PageCache,
+                     form_builder: &runners_world::FormBuilder,
+                     dump_html: &Option<String>)
+                     -> Result<recommendation::Recommendation, runners_world::ScrapeError> {
+    let url = form_builder.to_url();
+    let cached = cache.get(&url);
+    let body = match cached {
+        Some(ref b) => b.clone(),
+        None => try!(runners_world::fetch_page(client, &url)),
+    };
+    let scraped = runners_world::scrape(&Document::from(body.as_str())).map_err(|e| {
+        let mut stderr = std::io::stderr();
+        if let Some(ref path) = *dump_html {
+            match File::create(path).and_then(|mut f| f.write_all(body.as_bytes())) {
+                Ok(()) => {
+                    let _ = writeln!(&mut stderr, "The page was saved to {}", path);
+                }
+                Err(io) => {
+                    let _ = writeln!(&mut stderr, "Couldn't save the page to {}: {}", path, io);
+                }
+            }
+        }
+        e
+    });
+    let scraped = try!(scraped);
+    if cached.is_none() {
+        // Caching is best effort, a failure here shouldn't stop the run.
+        let _ = cache.put(&url, &body);
+    }
+    Ok(scraped)
+}
+
+/// Shows the pictures of the recommended garments, from head to toe.
+fn show_pictures(client: &Client, recommendation: &recommendation::Recommendation) {
+    let mut shown = Vec::new();
+    for picture in recommendation.items.iter().filter_map(|g| g.image.as_ref()) {
+        if shown.contains(&&picture.url) {
+            continue;
+        }
+        runners_world::display_inline_image(picture,
+                                            runners_world::download_img(client, &picture.url)
+                                                .map(|x| runners_world::to_base_64(&x)));
+        shown.push(&picture.url);
+    }
+}
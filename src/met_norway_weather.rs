@@ -0,0 +1,426 @@
+extern crate hyper;
+extern crate serde_json;
+extern crate time;
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use self::hyper::header::{Expires, HttpDate, IfModifiedSince, LastModified, UserAgent};
+use self::hyper::status::StatusCode;
+
+use cache;
+use config;
+use open_meteo_weather::{geocode, GEOCODING_URL};
+use weather::{local_time, nautical_utc_offset, parse_hours_from_local_time, user_agent, Client,
+              Condition, Observation, Place, Query, WeatherError, WeatherProvider};
+
+static MET_NORWAY_URL: &'static str = "https://api.met.no/weatherapi/locationforecast/2.0/";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Forecast {
+    pub geometry: Geometry,
+    pub properties: Properties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Geometry {
+    /// Longitude, latitude and altitude (in meters)
+    pub coordinates: Vec<f32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Properties {
+    pub timeseries: Vec<TimeStep>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TimeStep {
+    /// UTC time in ISO 8601, e.g.: 2016-12-15T09:00:00Z
+    pub time: String,
+    pub data: TimeStepData,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct TimeStepData {
+    pub instant: Instant,
+    pub next_1_hours: Option<Period>,
+    pub next_6_hours: Option<Period>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Instant {
+    pub details: InstantDetails,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct InstantDetails {
+    /// Temperature in celsius
+    pub air_temperature: f32,
+    /// Cloud cover as percentage
+    pub cloud_area_fraction: Option<f32>,
+    /// Humidity as percentage
+    pub relative_humidity: Option<f32>,
+    /// Wind speed in meters per second
+    pub wind_speed: f32,
+    /// Wind direction in degrees
+    wind_from_direction: Option<f32>,
+    /// UV index for cloud-free conditions
+    pub ultraviolet_index_clear_sky: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Period {
+    pub summary: Summary,
+    pub details: Option<PeriodDetails>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Summary {
+    /// e.g.: lightsnowshowers_day
+    pub symbol_code: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct PeriodDetails {
+    /// Precipitation amount in millimeters
+    pub precipitation_amount: Option<f32>,
+}
+
+/// A response as stored in the on-disk cache, together with the headers
+/// needed to honour MET Norway's caching rules.
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedResponse {
+    /// The `Expires` header, as an HTTP date
+    expires: String,
+    /// The `Last-Modified` header, as an HTTP date
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// The MET Norway locationforecast API. Its terms of service require every
+/// client to identify itself via the User-Agent, and to not ask again for
+/// the same location before the `Expires` time of the previous response.
+pub struct MetNorway {
+    base_url: String,
+    geocoding_url: String,
+    user_agent: String,
+    cache_dir: Option<PathBuf>,
+}
+
+impl MetNorway {
+    /// `contact` is an optional email or url which will be appended to the
+    /// User-Agent, as MET Norway asks for.
+    pub fn new(contact: &Option<String>) -> MetNorway {
+        MetNorway::with_urls(MET_NORWAY_URL,
+                             GEOCODING_URL,
+                             &user_agent(contact),
+                             config::cache_dir().map(|d| d.join("met_norway")))
+    }
+
+    pub fn with_urls(base_url: &str,
+                     geocoding_url: &str,
+                     user_agent: &str,
+                     cache_dir: Option<PathBuf>)
+                     -> MetNorway {
+        MetNorway {
+            base_url: base_url.to_owned(),
+            geocoding_url: geocoding_url.to_owned(),
+            user_agent: user_agent.to_owned(),
+            cache_dir: cache_dir,
+        }
+    }
+
+    /// Gets the compact forecast for the given coordinates, from the cache
+    /// if it didn't expire yet, asking the API for changes otherwise.
+    fn fetch_forecast(&self, client: &Client, lat: f32, lon: f32) -> Result<String, WeatherError> {
+        // MET Norway asks to not use more than 4 decimals.
+        let url = format!("{}compact?lat={:.4}&lon={:.4}", self.base_url, lat, lon);
+        let cache_file = self.cache_dir
+            .as_ref()
+            .map(|d| d.join(format!("{:.4}_{:.4}.json", lat, lon)));
+        let cached: Option<CachedResponse> = cache_file.as_ref().and_then(|f| cache::read_json(f));
+
+        if let Some(ref c) = cached {
+            if !is_expired(&c.expires) {
+                return Ok(c.body.clone());
+            }
+        }
+
+        let mut request = client.get(&url).header(UserAgent(self.user_agent.clone()));
+        let if_modified_since = cached.as_ref()
+            .and_then(|c| c.last_modified.as_ref())
+            .and_then(|lm| lm.parse::<HttpDate>().ok());
+        if let Some(lm) = if_modified_since {
+            request = request.header(IfModifiedSince(lm));
+        }
+
+        let mut response = try!(request.send());
+        let status = response.status;
+        let expires = response.headers.get::<Expires>().map(|e| format!("{}", e.0));
+        let last_modified = response.headers.get::<LastModified>().map(|lm| format!("{}", lm.0));
+        let (body, last_modified) = match (status, cached) {
+            (StatusCode::NotModified, Some(c)) => (c.body, last_modified.or(c.last_modified)),
+            (StatusCode::Ok, _) => {
+                let mut body = String::new();
+                let _ = try!(response.read_to_string(&mut body));
+                (body, last_modified)
+            }
+            _ => return Err(WeatherError::InvalidRequest(url, response)),
+        };
+
+        if let Some(ref f) = cache_file {
+            let entry = CachedResponse {
+                expires: expires.unwrap_or(format!("{}", HttpDate(time::now_utc()))),
+                last_modified: last_modified,
+                body: body.clone(),
+            };
+            // Caching is best effort, a failure here shouldn't stop the run.
+            let _ = cache::write_json(f, &entry);
+        }
+        Ok(body)
+    }
+}
+
+impl WeatherProvider for MetNorway {
+    fn name(&self) -> &'static str {
+        "met-norway"
+    }
+
+    fn current_weather(&self, client: &Client, query: &Query) -> Result<Observation, WeatherError> {
+        let place = try!(geocode(client, &self.geocoding_url, self.name(), query));
+        let body = try!(self.fetch_forecast(client, place.latitude, place.longitude));
+        let forecast: Forecast = try!(serde_json::from_str(&body));
+        let step = try!(current_step(&forecast.properties.timeseries, time::get_time())
+            .ok_or(WeatherError::UnexpectedResponse("MET Norway returned an empty forecast".to_owned())));
+        let utc_offset = nautical_utc_offset(place.longitude);
+        let mut obs = to_observation(&place.to_place(), step, utc_offset);
+        obs.elevation_m = forecast.geometry.coordinates.get(2).cloned();
+        Ok(obs)
+    }
+
+    fn hourly_forecast(&self,
+                       client: &Client,
+                       query: &Query)
+                       -> Result<Vec<Observation>, WeatherError> {
+        let place = try!(geocode(client, &self.geocoding_url, self.name(), query));
+        let body = try!(self.fetch_forecast(client, place.latitude, place.longitude));
+        let forecast: Forecast = try!(serde_json::from_str(&body));
+        let elevation = forecast.geometry.coordinates.get(2).cloned();
+        let utc_offset = nautical_utc_offset(place.longitude);
+        Ok(to_forecast(&place.to_place(), &forecast.properties.timeseries, utc_offset)
+            .into_iter()
+            .map(|mut o| {
+                o.elevation_m = elevation;
+                o
+            })
+            .collect())
+    }
+}
+
+fn is_expired(http_date: &str) -> bool {
+    http_date.parse::<HttpDate>()
+        .map(|d| d.0.to_timespec() <= time::get_time())
+        .unwrap_or(true)
+}
+
+/// Parses the UTC time of a time step.
+fn parse_time(iso_time: &str) -> Option<time::Timespec> {
+    time::strptime(iso_time, "%Y-%m-%dT%H:%M:%SZ").ok().map(|tm| tm.to_timespec())
+}
+
+/// Picks the most recent time step which is not in the future, or the
+/// first one if they all are.
+pub fn current_step(timeseries: &Vec<TimeStep>, now: time::Timespec) -> Option<&TimeStep> {
+    timeseries.iter()
+        .take_while(|s| parse_time(&s.time).map(|t| t <= now).unwrap_or(false))
+        .last()
+        .or(timeseries.first())
+}
+
+/// The hourly part of the time series. Further ahead, MET Norway only
+/// forecasts every 6 hours.
+pub fn to_forecast(place: &Place, timeseries: &Vec<TimeStep>, utc_offset: i64) -> Vec<Observation> {
+    timeseries.iter()
+        .take_while(|s| s.data.next_1_hours.is_some())
+        .map(|s| to_observation(place, s, utc_offset))
+        .collect()
+}
+
+/// The observation of a time step, at the place `utc_offset` seconds ahead of UTC.
+pub fn to_observation(place: &Place, step: &TimeStep, utc_offset: i64) -> Observation {
+    let place = Place { utc_offset_s: Some(utc_offset), ..place.clone() };
+    let details = &step.data.instant.details;
+    let symbol = step.data
+        .next_1_hours
+        .as_ref()
+        .or(step.data.next_6_hours.as_ref())
+        .map(|p| p.summary.symbol_code.clone())
+        .unwrap_or("cloudy".to_owned());
+    let utc = parse_time(&step.time).unwrap_or(time::get_time());
+    let local = local_time(utc.sec, utc_offset);
+    let local_hour = parse_hours_from_local_time(&local).unwrap_or(12);
+    Observation {
+        place: place,
+        local_time: local,
+        temp_c: details.air_temperature,
+        // MET Norway doesn't report any "feels like" temperature.
+        feelslike_c: details.air_temperature,
+        wind_kph: details.wind_speed * 3.6,
+        gust_kph: None,
+        visibility_km: None,
+        dew_point_c: None,
+        humidity: details.relative_humidity.map(|h| h.round() as u8),
+        cloud: details.cloud_area_fraction.map(|c| c.round() as u8),
+        precip_mm: step.data
+            .next_1_hours
+            .as_ref()
+            .and_then(|p| p.details.as_ref())
+            .and_then(|d| d.precipitation_amount),
+        precip_chance: None,
+        uv_index: details.ultraviolet_index_clear_sky,
+        elevation_m: None,
+        is_day: is_day(&symbol, local_hour),
+        condition: to_condition(&symbol),
+        condition_text: describe(&symbol),
+    }
+}
+
+/// Symbols come in a `_day`, `_night` and `_polartwilight` variant when the
+/// sun matters, otherwise we guess from the local hour.
+fn is_day(symbol_code: &str, local_hour: u8) -> bool {
+    if symbol_code.ends_with("_day") {
+        return true;
+    }
+    if symbol_code.ends_with("_night") || symbol_code.ends_with("_polartwilight") {
+        return false;
+    }
+    local_hour >= 7 && local_hour < 19
+}
+
+fn base_symbol(symbol_code: &str) -> &str {
+    symbol_code.split('_').next().unwrap_or(symbol_code)
+}
+
+// See https://api.met.no/weatherapi/weathericon/2.0/documentation
+pub fn to_condition(symbol_code: &str) -> Condition {
+    let symbol = base_symbol(symbol_code);
+    if symbol.contains("thunder") {
+        return Condition::Thunderstorm;
+    }
+    if symbol.contains("sleet") {
+        return Condition::Sleet;
+    }
+    if symbol.contains("snow") {
+        return Condition::Snow;
+    }
+    match symbol {
+        "clearsky" => Condition::Clear,
+        "fair" | "partlycloudy" => Condition::PartlyCloudy,
+        "cloudy" => Condition::Overcast,
+        "fog" => Condition::Fog,
+        "lightrain" | "lightrainshowers" => Condition::LightRain,
+        "heavyrain" | "heavyrainshowers" => Condition::HeavyRain,
+        // Assume rain otherwise
+        _ => Condition::Rain,
+    }
+}
+
+/// Turns a symbol code like `lightrainshowers_day` into "Light rain showers".
+pub fn describe(symbol_code: &str) -> String {
+    let mut symbol = base_symbol(symbol_code).to_owned();
+    for &(from, to) in [("clearsky", "clear sky"),
+                        ("partlycloudy", "partly cloudy"),
+                        ("andthunder", " and thunder"),
+                        ("showers", " showers"),
+                        ("light", "light "),
+                        ("heavy", "heavy ")]
+        .iter() {
+        symbol = symbol.replace(from, to);
+    }
+    let mut chars = symbol.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::serde_json;
+    use std::env;
+    use std::fs;
+    use test_server;
+    use weather::{Client, Condition, Query, WeatherProvider};
+
+    #[test]
+    fn identifies_itself_and_honours_the_cache_headers() {
+        let last_modified = "Mon, 15 Jan 2024 09:42:11 GMT";
+        let (url, requests) = test_server::serve(vec![
+            test_server::Reply {
+                status: "200 OK",
+                headers: vec![("Expires", "Thu, 01 Jan 1970 00:00:00 GMT".to_owned()),
+                              ("Last-Modified", last_modified.to_owned())],
+                body: include_str!("../fixtures/met_norway/compact.json").to_owned(),
+            },
+            test_server::Reply {
+                status: "304 Not Modified",
+                headers: vec![("Expires", "Fri, 01 Jan 2100 00:00:00 GMT".to_owned())],
+                body: String::new(),
+            },
+        ]);
+        let cache_dir = env::temp_dir().join("viktor-test-met-norway");
+        let _ = fs::remove_dir_all(&cache_dir);
+        let provider = MetNorway::with_urls(&format!("{}/", url),
+                                           &format!("{}/", url),
+                                           "viktor-tests/0.1 test@example.com",
+                                           Some(cache_dir.clone()));
+        let client = Client::new();
+        let query = Query::Coords(59.91394, 10.75224);
+
+        // The first response is already expired, so we ask again with If-Modified-Since...
+        let first = provider.current_weather(&client, &query).unwrap();
+        let second = provider.current_weather(&client, &query).unwrap();
+        // ...and now the cache is fresh, the test server wouldn't answer a third request.
+        let third = provider.current_weather(&client, &query).unwrap();
+
+        let first_request = requests.recv().unwrap();
+        assert!(first_request.contains("lat=59.9139&lon=10.7522"));
+        assert!(first_request.contains("User-Agent: viktor-tests/0.1 test@example.com"));
+        assert!(requests.recv().unwrap().contains(&format!("If-Modified-Since: {}", last_modified)));
+        for obs in vec![first, second, third] {
+            assert_eq!(obs.temp_c, -6.9);
+            assert_eq!(obs.humidity, Some(81));
+            assert_eq!(obs.condition, Condition::Snow);
+            assert_eq!(obs.condition_text, "Light snow");
+            assert_eq!(obs.elevation_m, Some(10.0));
+            assert!(obs.local_time.starts_with("2024-01-15"));
+        }
+        let _ = fs::remove_dir_all(&cache_dir);
+    }
+
+    #[test]
+    fn picks_the_latest_step_which_is_not_in_the_future() {
+        let forecast: Forecast =
+            serde_json::from_str(include_str!("../fixtures/met_norway/compact.json")).unwrap();
+        let steps = &forecast.properties.timeseries;
+        let at = |t: &str| parse_time(t).unwrap();
+        assert_eq!(current_step(steps, at("2024-01-15T11:30:00Z")).unwrap().time,
+                   "2024-01-15T11:00:00Z");
+        assert_eq!(current_step(steps, at("2024-01-15T08:00:00Z")).unwrap().time,
+                   "2024-01-15T10:00:00Z");
+        let forecast = to_forecast(&Place::default(), steps, 3600);
+        assert_eq!(forecast.len(), 3);
+        assert_eq!(forecast[0].local_time, "2024-01-15 11:00");
+        assert_eq!(forecast[2].temp_c, -6.9);
+    }
+
+    #[test]
+    fn can_map_symbol_codes() {
+        assert_eq!(to_condition("clearsky_night"), Condition::Clear);
+        assert_eq!(to_condition("lightrainshowers_day"), Condition::LightRain);
+        assert_eq!(to_condition("heavysleetandthunder"), Condition::Thunderstorm);
+        assert_eq!(to_condition("lightsleetshowers_polartwilight"), Condition::Sleet);
+        assert_eq!(describe("lightrainshowersandthunder_day"), "Light rain showers and thunder");
+    }
+}
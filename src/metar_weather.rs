@@ -0,0 +1,530 @@
+extern crate time;
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use weather::{is_daytime, local_time, nautical_utc_offset, parse_hours_from_local_time,
+              relative_humidity, Client, Condition, Observation, Place, Query, WeatherError,
+              WeatherProvider};
+
+/// Groups after which a report only carries trends and remarks.
+static END_MARKERS: [&'static str; 5] = ["RMK", "TEMPO", "BECMG", "NOSIG", "INTER"];
+
+static DESCRIPTORS: [(&'static str, &'static str); 8] =
+    [("MI", "shallow"),
+     ("BC", "patches of"),
+     ("PR", "partial"),
+     ("DR", "drifting"),
+     ("BL", "blowing"),
+     ("SH", "showers"),
+     ("TS", "thunderstorm"),
+     ("FZ", "freezing")];
+
+static PHENOMENA: [(&'static str, &'static str); 22] =
+    [("DZ", "drizzle"),
+     ("RA", "rain"),
+     ("SN", "snow"),
+     ("SG", "snow grains"),
+     ("IC", "ice crystals"),
+     ("PL", "ice pellets"),
+     ("GR", "hail"),
+     ("GS", "small hail"),
+     ("UP", "unknown precipitation"),
+     ("BR", "mist"),
+     ("FG", "fog"),
+     ("FU", "smoke"),
+     ("VA", "volcanic ash"),
+     ("DU", "dust"),
+     ("SA", "sand"),
+     ("HZ", "haze"),
+     ("PY", "spray"),
+     ("PO", "dust whirls"),
+     ("SQ", "squalls"),
+     ("FC", "funnel cloud"),
+     ("SS", "sandstorm"),
+     ("DS", "duststorm")];
+
+/// A decoded METAR report, e.g. `LICJ 181050Z 27012KT 9999 FEW030 18/11 Q1015`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metar {
+    /// ICAO code of the station
+    pub station: String,
+    /// Day of the month of the observation (UTC)
+    pub day: u8,
+    /// Hour of the observation (UTC)
+    pub hour: u8,
+    /// Minute of the observation (UTC)
+    pub minute: u8,
+    /// Wind direction in degrees, None if variable
+    pub wind_degree: Option<u16>,
+    /// Wind speed in kilometer per hour
+    pub wind_kph: f32,
+    /// Wind gusts in kilometer per hour
+    pub gust_kph: Option<f32>,
+    /// Prevailing visibility in kilometers
+    pub visibility_km: Option<f32>,
+    /// Present weather groups, e.g. `-SHRA`
+    pub weather: Vec<String>,
+    /// Cloud cover codes, e.g. `FEW` or `OVC`
+    pub clouds: Vec<String>,
+    /// Temperature in celsius
+    pub temp_c: f32,
+    /// Dew point in celsius
+    pub dew_point_c: Option<f32>,
+    /// Pressure in millibars
+    pub pressure_mb: Option<f32>,
+}
+
+pub fn parse(report: &str) -> Result<Metar, WeatherError> {
+    let invalid = |why: &str| WeatherError::InvalidMetar(format!("{}: '{}'", why, report.trim()));
+    let tokens: Vec<&str> = report.split_whitespace()
+        .skip_while(|t| *t == "METAR" || *t == "SPECI")
+        .take_while(|t| !END_MARKERS.contains(t))
+        .collect();
+    if tokens.len() < 2 {
+        return Err(invalid("Not a METAR report"));
+    }
+
+    let mut metar = Metar { station: tokens[0].to_owned(), ..Default::default() };
+    match parse_time(tokens[1]) {
+        None => return Err(invalid("Missing observation time")),
+        Some((day, hour, minute)) => {
+            metar.day = day;
+            metar.hour = hour;
+            metar.minute = minute;
+        }
+    }
+
+    let mut temp_c = None;
+    // Whole statute miles come in their own group, e.g. `1 1/2SM`.
+    let mut whole_miles = 0.0;
+    for &token in tokens.iter().skip(2) {
+        if let Some((degree, speed, gust)) = parse_wind(token) {
+            metar.wind_degree = degree;
+            metar.wind_kph = speed;
+            metar.gust_kph = gust;
+        } else if token == "CAVOK" {
+            metar.visibility_km = Some(10.0);
+            metar.clouds.push("NSC".to_owned());
+        } else if token.len() == 1 && token.parse::<f32>().is_ok() {
+            whole_miles = token.parse::<f32>().unwrap_or(0.0);
+        } else if let Some(km) = parse_visibility(token, whole_miles) {
+            // Only the first group is the prevailing visibility.
+            if metar.visibility_km.is_none() {
+                metar.visibility_km = Some(km);
+            }
+        } else if let Some((temp, dew_point)) = parse_temperatures(token) {
+            temp_c = Some(temp);
+            metar.dew_point_c = dew_point;
+        } else if let Some(pressure) = parse_pressure(token) {
+            metar.pressure_mb = Some(pressure);
+        } else if let Some(cover) = parse_cloud(token) {
+            metar.clouds.push(cover.to_owned());
+        } else if is_present_weather(token) {
+            metar.weather.push(token.to_owned());
+        }
+        // Anything else (runway visual range, variable wind sector, ...) is ignored.
+    }
+
+    match temp_c {
+        None => Err(invalid("Missing temperature group")),
+        Some(t) => {
+            metar.temp_c = t;
+            Ok(metar)
+        }
+    }
+}
+
+/// METAR groups are plain ASCII: anything else can't be one, and the parsers
+/// below, slicing them by byte, would cut through its characters.
+fn is_ascii(token: &str) -> bool {
+    token.bytes().all(|b| b < 0x80)
+}
+
+// DDHHMMZ
+fn parse_time(token: &str) -> Option<(u8, u8, u8)> {
+    if token.len() != 7 || !is_ascii(token) || !token.ends_with('Z') {
+        return None;
+    }
+    match (token[0..2].parse(), token[2..4].parse(), token[4..6].parse()) {
+        (Ok(d), Ok(h), Ok(m)) => Some((d, h, m)),
+        _ => None,
+    }
+}
+
+// dddff(Ggg)KT, with MPS and KMH as alternative units, and VRB as direction.
+fn parse_wind(token: &str) -> Option<(Option<u16>, f32, Option<f32>)> {
+    let (body, to_kph) = if token.ends_with("KT") {
+        (&token[..token.len() - 2], 1.852)
+    } else if token.ends_with("MPS") {
+        (&token[..token.len() - 3], 3.6)
+    } else if token.ends_with("KMH") {
+        (&token[..token.len() - 3], 1.0)
+    } else {
+        return None;
+    };
+    if body.len() < 5 || !is_ascii(body) {
+        return None;
+    }
+    let degree = if &body[..3] == "VRB" {
+        None
+    } else {
+        match body[..3].parse::<u16>() {
+            Ok(d) => Some(d),
+            Err(_) => return None,
+        }
+    };
+    let mut speeds = body[3..].split('G');
+    let speed = match speeds.next().and_then(|s| s.parse::<f32>().ok()) {
+        Some(s) => s,
+        None => return None,
+    };
+    let gust = speeds.next().and_then(|g| g.parse::<f32>().ok());
+    Some((degree, speed * to_kph, gust.map(|g| g * to_kph)))
+}
+
+// Either meters (`9999` meaning 10km or more) or statute miles (`10SM`, `1/2SM`, `P6SM`).
+fn parse_visibility(token: &str, whole_miles: f32) -> Option<f32> {
+    if !is_ascii(token) {
+        return None;
+    }
+    if token.ends_with("SM") {
+        let miles = token[..token.len() - 2].trim_left_matches(|c: char| c == 'P' || c == 'M');
+        let value = match *miles.split('/').collect::<Vec<_>>().as_slice() {
+            [n] => n.parse::<f32>().ok(),
+            [n, d] => {
+                match (n.parse::<f32>(), d.parse::<f32>()) {
+                    (Ok(n), Ok(d)) if d > 0.0 => Some(whole_miles + n / d),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        return value.map(|m| m * 1.609344);
+    }
+    let meters = token.trim_right_matches("NDV");
+    if meters.len() == 4 && meters.chars().all(|c| c.is_digit(10)) {
+        return meters.parse::<f32>().ok().map(|m| if m >= 9999.0 { 10.0 } else { m / 1000.0 });
+    }
+    None
+}
+
+// TT/DD, with M marking negative values, e.g. `M03/M07`. The dew point may be missing.
+fn parse_temperatures(token: &str) -> Option<(f32, Option<f32>)> {
+    match *token.split('/').collect::<Vec<_>>().as_slice() {
+        [temp, dew_point] => parse_signed(temp).map(|t| (t, parse_signed(dew_point))),
+        _ => None,
+    }
+}
+
+fn parse_signed(value: &str) -> Option<f32> {
+    let (sign, digits) = if value.starts_with('M') {
+        (-1.0, &value[1..])
+    } else {
+        (1.0, value)
+    };
+    if digits.len() != 2 || !digits.chars().all(|c| c.is_digit(10)) {
+        return None;
+    }
+    digits.parse::<f32>().ok().map(|v| sign * v)
+}
+
+// Q1015 (hPa) or A3012 (hundredths of inHg)
+fn parse_pressure(token: &str) -> Option<f32> {
+    if token.len() != 5 || !is_ascii(token) || !token[1..].chars().all(|c| c.is_digit(10)) {
+        return None;
+    }
+    let value = token[1..].parse::<f32>().unwrap_or(0.0);
+    match &token[..1] {
+        "Q" => Some(value),
+        "A" => Some(value / 100.0 * 33.8639),
+        _ => None,
+    }
+}
+
+fn parse_cloud(token: &str) -> Option<&str> {
+    for cover in ["SKC", "CLR", "NSC", "NCD", "FEW", "SCT", "BKN", "OVC", "VV"].iter() {
+        if token.starts_with(cover) {
+            return Some(*cover);
+        }
+    }
+    None
+}
+
+fn strip_intensity(group: &str) -> &str {
+    group.trim_left_matches(|c: char| c == '-' || c == '+').trim_left_matches("VC")
+}
+
+/// Splits a present weather group into its two-letter codes.
+fn codes(group: &str) -> Vec<&str> {
+    let stripped = strip_intensity(group);
+    (0..stripped.len() / 2).map(|i| &stripped[i * 2..i * 2 + 2]).collect()
+}
+
+fn is_known_code(code: &str) -> bool {
+    DESCRIPTORS.iter().chain(PHENOMENA.iter()).any(|&(c, _)| c == code)
+}
+
+fn is_present_weather(token: &str) -> bool {
+    let stripped = strip_intensity(token);
+    is_ascii(token) && !stripped.is_empty() && stripped.len() % 2 == 0 &&
+    codes(token).iter().all(|c| is_known_code(c))
+}
+
+pub fn to_condition(metar: &Metar) -> Condition {
+    let all_codes: Vec<&str> = metar.weather.iter().flat_map(|g| codes(g)).collect();
+    let has = |code: &str| all_codes.contains(&code);
+    let rain_intensity = metar.weather
+        .iter()
+        .filter(|g| codes(g).contains(&"RA"))
+        .map(|g| g.chars().next().unwrap_or(' '))
+        .next();
+
+    if has("TS") {
+        return Condition::Thunderstorm;
+    }
+    if (has("FZ") && (has("RA") || has("DZ"))) || has("PL") || has("GR") || has("GS") ||
+       (has("RA") && has("SN")) {
+        return Condition::Sleet;
+    }
+    if has("SN") || has("SG") {
+        return Condition::Snow;
+    }
+    match rain_intensity {
+        Some('-') => return Condition::LightRain,
+        Some('+') => return Condition::HeavyRain,
+        Some(_) => return Condition::Rain,
+        None => (),
+    }
+    if has("DZ") {
+        return Condition::Drizzle;
+    }
+    if has("FG") || has("BR") || has("HZ") || has("FU") {
+        return Condition::Fog;
+    }
+    let covers: Vec<&str> = metar.clouds.iter().map(|c| c.as_str()).collect();
+    if covers.contains(&"OVC") || covers.contains(&"BKN") || covers.contains(&"VV") {
+        return Condition::Overcast;
+    }
+    if covers.contains(&"SCT") || covers.contains(&"FEW") {
+        return Condition::PartlyCloudy;
+    }
+    Condition::Clear
+}
+
+/// A readable description of the present weather, e.g. "Light rain showers, mist".
+pub fn describe(metar: &Metar) -> String {
+    if metar.weather.is_empty() {
+        return match to_condition(metar) {
+            Condition::Overcast => "Overcast",
+            Condition::PartlyCloudy => "Partly cloudy",
+            _ => "Clear",
+        }
+        .to_owned();
+    }
+    let groups: Vec<String> = metar.weather.iter().map(|g| describe_group(g)).collect();
+    let text = groups.join(", ");
+    let mut chars = text.chars();
+    match chars.next() {
+        None => String::new(),
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+    }
+}
+
+fn describe_group(group: &str) -> String {
+    let mut words = Vec::new();
+    if group.starts_with('-') {
+        words.push("light");
+    } else if group.starts_with('+') {
+        words.push("heavy");
+    }
+    let group_codes = codes(group);
+    for code in group_codes.iter() {
+        if *code != "SH" && *code != "TS" {
+            let word = DESCRIPTORS.iter().chain(PHENOMENA.iter()).find(|&&(c, _)| c == *code);
+            if let Some(&(_, w)) = word {
+                words.push(w);
+            }
+        }
+    }
+    if group_codes.contains(&"SH") {
+        words.push("showers");
+    }
+    if group_codes.contains(&"TS") {
+        words.push(if group_codes.len() > 1 { "with thunderstorm" } else { "thunderstorm" });
+    }
+    let without_intensity = group.trim_left_matches(|c: char| c == '-' || c == '+');
+    if strip_intensity(group).len() < without_intensity.len() {
+        words.push("nearby");
+    }
+    words.join(" ")
+}
+
+/// Observation time (UTC), assuming the report is from the current month
+/// (or the previous one, if the day is still to come).
+fn observation_time(metar: &Metar, now_utc: &time::Tm) -> time::Timespec {
+    let mut tm = *now_utc;
+    tm.tm_mday = metar.day as i32;
+    tm.tm_hour = metar.hour as i32;
+    tm.tm_min = metar.minute as i32;
+    tm.tm_sec = 0;
+    tm.tm_nsec = 0;
+    if tm.tm_mday > now_utc.tm_mday {
+        if tm.tm_mon == 0 {
+            tm.tm_mon = 11;
+            tm.tm_year -= 1;
+        } else {
+            tm.tm_mon -= 1;
+        }
+    }
+    tm.to_timespec()
+}
+
+/// The observation, in the local time of a station `utc_offset` seconds
+/// ahead of UTC.
+pub fn to_observation(metar: &Metar, now_utc: &time::Tm, utc_offset: i64) -> Observation {
+    let local = local_time(observation_time(metar, now_utc).sec, utc_offset);
+    // Without a sun position, go by the local hour.
+    let is_day = is_daytime(parse_hours_from_local_time(&local).unwrap_or(12));
+    Observation {
+        place: Place {
+            name: metar.station.clone(),
+            country: String::new(),
+            utc_offset_s: Some(utc_offset),
+        },
+        local_time: local,
+        temp_c: metar.temp_c,
+        // METAR doesn't carry any "feels like" temperature.
+        feelslike_c: metar.temp_c,
+        wind_kph: metar.wind_kph,
+        gust_kph: metar.gust_kph,
+        visibility_km: metar.visibility_km,
+        dew_point_c: metar.dew_point_c,
+        humidity: metar.dew_point_c.map(|d| relative_humidity(metar.temp_c, d).round() as u8),
+        cloud: None,
+        precip_mm: None,
+        precip_chance: None,
+        uv_index: None,
+        elevation_m: None,
+        is_day: is_day,
+        condition: to_condition(metar),
+        condition_text: describe(metar),
+    }
+}
+
+/// Serves a METAR report given by the user, without any network access.
+pub struct MetarSource {
+    metar: Metar,
+}
+
+impl MetarSource {
+    pub fn from_report(report: &str) -> Result<MetarSource, WeatherError> {
+        parse(report).map(|m| MetarSource { metar: m })
+    }
+
+    /// Reads the last report in a station file, like the ones found under
+    /// https://tgftp.nws.noaa.gov/data/observations/metar/stations/
+    pub fn from_file(path: &Path) -> Result<MetarSource, WeatherError> {
+        let mut contents = String::new();
+        let mut file = try!(File::open(path));
+        let _ = try!(file.read_to_string(&mut contents));
+        let report = contents.lines().filter(|l| !l.trim().is_empty()).last().unwrap_or("");
+        MetarSource::from_report(report)
+    }
+}
+
+impl WeatherProvider for MetarSource {
+    fn name(&self) -> &'static str {
+        "metar"
+    }
+
+    /// Reports don't tell where the station is: its local time is the one
+    /// of the longitude given with `--coords`, or UTC otherwise.
+    fn current_weather(&self, _client: &Client, query: &Query) -> Result<Observation, WeatherError> {
+        let utc_offset = match *query {
+            Query::Coords(_, longitude) => nautical_utc_offset(longitude),
+            _ => 0,
+        };
+        Ok(to_observation(&self.metar, &time::now_utc(), utc_offset))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::time;
+    use weather::{nautical_utc_offset, Condition};
+
+    #[test]
+    fn can_parse_a_simple_report() {
+        let metar = parse("LICJ 181050Z 27012KT 9999 FEW030 18/11 Q1015").unwrap();
+        assert_eq!(metar.station, "LICJ");
+        assert_eq!((metar.day, metar.hour, metar.minute), (18, 10, 50));
+        assert_eq!(metar.wind_degree, Some(270));
+        assert!((metar.wind_kph - 22.224).abs() < 0.01);
+        assert_eq!(metar.gust_kph, None);
+        assert_eq!(metar.visibility_km, Some(10.0));
+        assert_eq!(metar.clouds, vec!["FEW".to_owned()]);
+        assert_eq!(metar.temp_c, 18.0);
+        assert_eq!(metar.dew_point_c, Some(11.0));
+        assert_eq!(metar.pressure_mb, Some(1015.0));
+        assert_eq!(to_condition(&metar), Condition::PartlyCloudy);
+    }
+
+    #[test]
+    fn can_parse_gusts_weather_and_us_units() {
+        let metar = parse("METAR KBOS 181054Z 05018G29KT 1 1/2SM -RA BR OVC008 M01/M03 A2992 RMK AO2")
+            .unwrap();
+        assert!((metar.wind_kph - 33.336).abs() < 0.01);
+        assert!((metar.gust_kph.unwrap() - 53.708).abs() < 0.01);
+        assert!((metar.visibility_km.unwrap() - 2.414).abs() < 0.01);
+        assert_eq!(metar.weather, vec!["-RA".to_owned(), "BR".to_owned()]);
+        assert_eq!(metar.temp_c, -1.0);
+        assert_eq!(metar.dew_point_c, Some(-3.0));
+        assert!((metar.pressure_mb.unwrap() - 1013.2).abs() < 0.1);
+        assert_eq!(to_condition(&metar), Condition::LightRain);
+        assert_eq!(describe(&metar), "Light rain, mist");
+    }
+
+    #[test]
+    fn can_map_present_weather() {
+        let condition = |r: &str| to_condition(&parse(r).unwrap());
+        assert_eq!(condition("EGLL 181050Z 22015KT 3000 +TSRA BKN010CB 14/13 Q1002"),
+                   Condition::Thunderstorm);
+        assert_eq!(condition("ENGM 181050Z VRB03KT 0800 SN FG VV002 M05/M06 Q0998"),
+                   Condition::Snow);
+        assert_eq!(condition("LIRF 181050Z 00000KT 0300 FG NSC 08/08 Q1021"), Condition::Fog);
+        assert_eq!(condition("LICJ 181050Z 27012KT CAVOK 18/11 Q1015"), Condition::Clear);
+    }
+
+    #[test]
+    fn ignores_groups_which_are_not_ascii() {
+        assert!(parse("LICJ 1é345Z 27012KT 18/11").is_err());
+        let metar = parse("LICJ 181050Z 12é1KT é101 1é2SM -RéA 18/11").unwrap();
+        assert_eq!(metar.wind_kph, 0.0);
+        assert_eq!(metar.visibility_km, None);
+        assert_eq!(metar.pressure_mb, None);
+        assert!(metar.weather.is_empty());
+        assert_eq!(metar.temp_c, 18.0);
+    }
+
+    #[test]
+    fn tells_the_time_of_the_station() {
+        let metar = parse("KBOS 181054Z 05018G29KT 10SM FEW250 12/03 A2992").unwrap();
+        let now = time::strptime("2026-10-18 11:00", "%Y-%m-%d %H:%M").unwrap();
+        let utc = to_observation(&metar, &now, 0);
+        assert_eq!(utc.local_time, "2026-10-18 10:54");
+        assert_eq!(utc.place.utc_offset_s, Some(0));
+        assert!(utc.is_day);
+        let boston = to_observation(&metar, &now, nautical_utc_offset(-71.0));
+        assert_eq!(boston.local_time, "2026-10-18 05:54");
+        assert!(!boston.is_day);
+    }
+
+    #[test]
+    fn rejects_reports_without_temperature() {
+        assert!(parse("LICJ 181050Z 27012KT 9999 FEW030 Q1015").is_err());
+        assert!(parse("hello").is_err());
+    }
+}
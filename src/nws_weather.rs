@@ -0,0 +1,531 @@
+extern crate hyper;
+extern crate serde_json;
+extern crate time;
+
+use std::cmp;
+use std::io::Read;
+use std::path::PathBuf;
+
+use self::hyper::header::UserAgent;
+
+use cache;
+use config;
+use open_meteo_weather::{geocode, GEOCODING_URL};
+use weather::{local_time, nautical_utc_offset, user_agent, Client, Condition, Observation, Place,
+              Query, WeatherError, WeatherProvider};
+
+static NWS_URL: &'static str = "https://api.weather.gov/";
+
+/// A value together with its WMO unit, e.g. `wmoUnit:degC`. The value is
+/// missing when the station didn't report it.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct QuantitativeValue {
+    #[serde(rename="unitCode")]
+    pub unit_code: String,
+    pub value: Option<f32>,
+}
+
+impl QuantitativeValue {
+    fn unit(&self) -> &str {
+        self.unit_code.split(':').last().unwrap_or("")
+    }
+
+    pub fn celsius(&self) -> Option<f32> {
+        self.value.and_then(|v| {
+            match self.unit() {
+                "degC" => Some(v),
+                "degF" => Some((v - 32.0) * 5.0 / 9.0),
+                "K" => Some(v - 273.15),
+                _ => None,
+            }
+        })
+    }
+
+    pub fn kph(&self) -> Option<f32> {
+        self.value.and_then(|v| {
+            match self.unit() {
+                "km_h-1" => Some(v),
+                "m_s-1" => Some(v * 3.6),
+                "kn" => Some(v * 1.852),
+                _ => None,
+            }
+        })
+    }
+
+    pub fn meters(&self) -> Option<f32> {
+        self.value.and_then(|v| if self.unit() == "m" { Some(v) } else { None })
+    }
+
+    pub fn percent(&self) -> Option<f32> {
+        self.value.and_then(|v| if self.unit() == "percent" { Some(v) } else { None })
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Point {
+    properties: PointProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct PointProperties {
+    /// Url listing the observation stations, nearest first
+    #[serde(rename="observationStations")]
+    observation_stations: String,
+    /// Url of the hourly forecast for the grid point
+    #[serde(rename="forecastHourly")]
+    forecast_hourly: String,
+    #[serde(rename="relativeLocation")]
+    relative_location: RelativeLocation,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct RelativeLocation {
+    properties: RelativeLocationProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct RelativeLocationProperties {
+    city: String,
+    state: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct Stations {
+    features: Vec<StationFeature>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct StationFeature {
+    /// Url of the station
+    id: String,
+    #[serde(default)]
+    properties: StationProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct StationProperties {
+    #[serde(default)]
+    elevation: QuantitativeValue,
+}
+
+/// The outcome of the points -> stations lookup, which is cached on disk
+/// as it only depends on the location.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Station {
+    observations_url: String,
+    forecast_hourly_url: String,
+    city: String,
+    state: String,
+    /// Elevation of the station in meters (missing from older caches)
+    elevation_m: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct LatestObservation {
+    pub properties: ObservationProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ObservationProperties {
+    /// UTC time of the observation, e.g.: 2016-12-15T09:52:00+00:00
+    pub timestamp: String,
+    #[serde(rename="textDescription")]
+    pub text_description: String,
+    /// Url of the icon, e.g.: https://api.weather.gov/icons/land/day/rain,40?size=medium
+    pub icon: Option<String>,
+    pub temperature: QuantitativeValue,
+    pub dewpoint: QuantitativeValue,
+    #[serde(rename="windSpeed")]
+    pub wind_speed: QuantitativeValue,
+    #[serde(rename="windGust")]
+    pub wind_gust: QuantitativeValue,
+    /// Visibility in meters
+    pub visibility: QuantitativeValue,
+    #[serde(rename="relativeHumidity")]
+    pub relative_humidity: QuantitativeValue,
+    /// Only reported when it's cold enough
+    #[serde(rename="windChill", default)]
+    pub wind_chill: QuantitativeValue,
+    /// Only reported when it's hot enough
+    #[serde(rename="heatIndex", default)]
+    pub heat_index: QuantitativeValue,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HourlyForecast {
+    pub properties: HourlyForecastProperties,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HourlyForecastProperties {
+    pub periods: Vec<ForecastPeriod>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ForecastPeriod {
+    /// Local time in ISO 8601, e.g.: 2016-12-15T09:00:00-05:00
+    #[serde(rename="startTime")]
+    pub start_time: String,
+    #[serde(rename="isDaytime")]
+    pub is_daytime: bool,
+    pub temperature: f32,
+    /// "F" or "C"
+    #[serde(rename="temperatureUnit")]
+    pub temperature_unit: String,
+    /// e.g.: "10 mph", or "5 to 10 mph"
+    #[serde(rename="windSpeed")]
+    pub wind_speed: String,
+    #[serde(rename="shortForecast")]
+    pub short_forecast: String,
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub dewpoint: QuantitativeValue,
+    #[serde(rename="relativeHumidity", default)]
+    pub relative_humidity: QuantitativeValue,
+    #[serde(rename="probabilityOfPrecipitation", default)]
+    pub probability_of_precipitation: QuantitativeValue,
+}
+
+/// The US National Weather Service API. Finding the current weather takes
+/// two hops (coordinates to grid point, grid point to nearest station)
+/// before the actual observation, so the station is cached per location.
+pub struct Nws {
+    base_url: String,
+    geocoding_url: String,
+    user_agent: String,
+    cache_dir: Option<PathBuf>,
+}
+
+impl Nws {
+    pub fn new(contact: &Option<String>) -> Nws {
+        Nws::with_urls(NWS_URL,
+                       GEOCODING_URL,
+                       &user_agent(contact),
+                       config::cache_dir().map(|d| d.join("nws")))
+    }
+
+    pub fn with_urls(base_url: &str,
+                     geocoding_url: &str,
+                     user_agent: &str,
+                     cache_dir: Option<PathBuf>)
+                     -> Nws {
+        Nws {
+            base_url: base_url.to_owned(),
+            geocoding_url: geocoding_url.to_owned(),
+            user_agent: user_agent.to_owned(),
+            cache_dir: cache_dir,
+        }
+    }
+
+    fn get(&self, client: &Client, url: &str) -> Result<String, WeatherError> {
+        let mut response = try!(client.get(url)
+            .header(UserAgent(self.user_agent.clone()))
+            .send());
+        if response.status != hyper::status::StatusCode::Ok {
+            return Err(WeatherError::InvalidRequest(url.to_owned(), response));
+        }
+        let mut body = String::new();
+        let _ = try!(response.read_to_string(&mut body));
+        Ok(body)
+    }
+
+    /// Finds the station nearest to the given coordinates.
+    fn station(&self, client: &Client, lat: f32, lon: f32) -> Result<Station, WeatherError> {
+        // The points endpoint redirects anything with more than 4 decimals.
+        let key = format!("{:.4},{:.4}", lat, lon);
+        let cache_file = self.cache_dir.as_ref().map(|d| d.join(format!("{}.json", key)));
+        if let Some(station) = cache_file.as_ref().and_then(|f| cache::read_json(f)) {
+            return Ok(station);
+        }
+
+        let point_body = try!(self.get(client, &format!("{}points/{}", self.base_url, key)));
+        let point: Point = try!(serde_json::from_str(&point_body));
+        let stations_body = try!(self.get(client, &point.properties.observation_stations));
+        let stations: Stations = try!(serde_json::from_str(&stations_body));
+        let nearest = try!(stations.features
+            .first()
+            .ok_or(WeatherError::LocationNotFound(format!("No NWS station near {}", key))));
+        let location = &point.properties.relative_location.properties;
+        let station = Station {
+            observations_url: format!("{}/observations/latest", nearest.id),
+            forecast_hourly_url: point.properties.forecast_hourly.clone(),
+            city: location.city.clone(),
+            state: location.state.clone(),
+            elevation_m: nearest.properties.elevation.meters(),
+        };
+        if let Some(ref f) = cache_file {
+            // Caching is best effort, a failure here shouldn't stop the run.
+            let _ = cache::write_json(f, &station);
+        }
+        Ok(station)
+    }
+
+    /// How far ahead of UTC the station's place is now, in seconds, daylight
+    /// saving included. The observations don't tell, the hourly forecast
+    /// periods do: the nautical offset of the longitude is only the fallback.
+    fn utc_offset_now(&self, client: &Client, station: &Station, longitude: f32) -> i64 {
+        self.get(client, &station.forecast_hourly_url)
+            .ok()
+            .and_then(|body| serde_json::from_str::<HourlyForecast>(&body).ok())
+            .and_then(|f| f.properties.periods.into_iter().next())
+            .and_then(|p| utc_offset(&p.start_time))
+            .unwrap_or(nautical_utc_offset(longitude))
+    }
+}
+
+impl WeatherProvider for Nws {
+    fn name(&self) -> &'static str {
+        "nws"
+    }
+
+    fn current_weather(&self, client: &Client, query: &Query) -> Result<Observation, WeatherError> {
+        let place = try!(geocode(client, &self.geocoding_url, self.name(), query));
+        let station = try!(self.station(client, place.latitude, place.longitude));
+        let body = try!(self.get(client, &station.observations_url));
+        let latest: LatestObservation = try!(serde_json::from_str(&body));
+        let utc_offset = self.utc_offset_now(client, &station, place.longitude);
+        let place = Place { utc_offset_s: Some(utc_offset), ..station_place(&station) };
+        to_observation(&place, &latest.properties, utc_offset).map(|mut o| {
+            o.elevation_m = station.elevation_m;
+            o
+        })
+    }
+
+    fn hourly_forecast(&self,
+                       client: &Client,
+                       query: &Query)
+                       -> Result<Vec<Observation>, WeatherError> {
+        let place = try!(geocode(client, &self.geocoding_url, self.name(), query));
+        let station = try!(self.station(client, place.latitude, place.longitude));
+        let body = try!(self.get(client, &station.forecast_hourly_url));
+        let forecast: HourlyForecast = try!(serde_json::from_str(&body));
+        let place = Place {
+            utc_offset_s: forecast.properties.periods.first().and_then(|p| utc_offset(&p.start_time)),
+            ..station_place(&station)
+        };
+        Ok(forecast.properties
+            .periods
+            .iter()
+            .map(|p| {
+                let mut obs = to_forecast_observation(&place, p);
+                obs.elevation_m = station.elevation_m;
+                obs
+            })
+            .collect())
+    }
+}
+
+fn station_place(station: &Station) -> Place {
+    Place {
+        name: format!("{}, {}", station.city, station.state),
+        country: "US".to_owned(),
+        utc_offset_s: None,
+    }
+}
+
+/// The UTC offset of an ISO 8601 time, in seconds, e.g. -18000 for
+/// 2016-12-15T09:00:00-05:00.
+fn utc_offset(iso_time: &str) -> Option<i64> {
+    let zone: String = iso_time.chars().skip(19).collect();
+    if zone == "Z" {
+        return Some(0);
+    }
+    let sign = match zone.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return None,
+    };
+    match *zone[1..].split(':').collect::<Vec<_>>().as_slice() {
+        [h, m] => {
+            match (h.parse::<i64>(), m.parse::<i64>()) {
+                (Ok(h), Ok(m)) => Some(sign * (h * 3600 + m * 60)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+pub fn to_forecast_observation(place: &Place, period: &ForecastPeriod) -> Observation {
+    let temp_c = if period.temperature_unit == "F" {
+        (period.temperature - 32.0) * 5.0 / 9.0
+    } else {
+        period.temperature
+    };
+    let icon = period.icon.clone().unwrap_or(String::new());
+    Observation {
+        place: place.clone(),
+        // Already local to the grid point, so the offset can be dropped.
+        local_time: period.start_time.chars().take(16).collect::<String>().replace("T", " "),
+        temp_c: temp_c,
+        feelslike_c: temp_c,
+        wind_kph: wind_kph(&period.wind_speed),
+        gust_kph: None,
+        visibility_km: None,
+        dew_point_c: period.dewpoint.celsius(),
+        humidity: period.relative_humidity.percent().map(|h| h.round() as u8),
+        cloud: None,
+        precip_mm: None,
+        precip_chance: period.probability_of_precipitation.percent().map(|p| p.round() as u8),
+        uv_index: None,
+        elevation_m: None,
+        is_day: period.is_daytime,
+        condition: to_condition(icon_code(&icon)),
+        condition_text: period.short_forecast.clone(),
+    }
+}
+
+/// Parses a forecast wind speed like "5 to 10 mph", taking the highest value.
+fn wind_kph(wind_speed: &str) -> f32 {
+    let speed = wind_speed.split_whitespace()
+        .filter_map(|w| w.parse::<f32>().ok())
+        .last()
+        .unwrap_or(0.0);
+    if wind_speed.contains("mph") {
+        speed * 1.609344
+    } else {
+        speed
+    }
+}
+
+/// The observation of a station `utc_offset` seconds ahead of UTC.
+pub fn to_observation(place: &Place,
+                      obs: &ObservationProperties,
+                      utc_offset: i64)
+                      -> Result<Observation, WeatherError> {
+    let temp_c = try!(obs.temperature
+        .celsius()
+        .ok_or(WeatherError::UnexpectedResponse("The NWS station reported no temperature".to_owned())));
+    let icon = obs.icon.clone().unwrap_or(String::new());
+    let utc = time::strptime(&obs.timestamp[..cmp::min(19, obs.timestamp.len())], "%Y-%m-%dT%H:%M:%S")
+        .map(|tm| tm.to_timespec())
+        .unwrap_or(time::get_time());
+    Ok(Observation {
+        place: place.clone(),
+        local_time: local_time(utc.sec, utc_offset),
+        temp_c: temp_c,
+        feelslike_c: obs.wind_chill.celsius().or(obs.heat_index.celsius()).unwrap_or(temp_c),
+        wind_kph: obs.wind_speed.kph().unwrap_or(0.0),
+        gust_kph: obs.wind_gust.kph(),
+        visibility_km: obs.visibility.value.map(|m| m / 1000.0),
+        dew_point_c: obs.dewpoint.celsius(),
+        humidity: obs.relative_humidity.percent().map(|h| h.round() as u8),
+        cloud: None,
+        precip_mm: None,
+        precip_chance: None,
+        uv_index: None,
+        elevation_m: None,
+        is_day: !icon.contains("/night/"),
+        condition: to_condition(icon_code(&icon)),
+        condition_text: obs.text_description.clone(),
+    })
+}
+
+/// Extracts the condition code out of an icon url, e.g. `rain` out of
+/// https://api.weather.gov/icons/land/day/rain,40?size=medium
+fn icon_code(icon_url: &str) -> &str {
+    let last = icon_url.split('/').last().unwrap_or("");
+    last.split(|c| c == ',' || c == '?').next().unwrap_or("")
+}
+
+// See https://api.weather.gov/icons
+pub fn to_condition(icon_code: &str) -> Condition {
+    match icon_code {
+        "skc" | "few" | "wind_skc" | "wind_few" | "hot" | "cold" => Condition::Clear,
+        "sct" | "wind_sct" => Condition::PartlyCloudy,
+        "bkn" | "ovc" | "wind_bkn" | "wind_ovc" => Condition::Overcast,
+        "fog" | "haze" | "smoke" | "dust" => Condition::Fog,
+        "rain_showers" | "rain_showers_hi" => Condition::LightRain,
+        "rain" => Condition::Rain,
+        "tropical_storm" | "hurricane" => Condition::HeavyRain,
+        "tsra" | "tsra_sct" | "tsra_hi" | "tornado" => Condition::Thunderstorm,
+        "rain_sleet" | "sleet" | "fzra" | "rain_fzra" | "snow_fzra" | "snow_sleet" => Condition::Sleet,
+        "snow" | "rain_snow" | "blizzard" => Condition::Snow,
+        // Assume cloudy otherwise
+        _ => Condition::Overcast,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use test_server;
+    use weather::{Client, Condition, Query, WeatherProvider};
+
+    fn fixture(contents: &str, url: &str) -> test_server::Reply {
+        test_server::ok(&contents.replace("https://api.weather.gov", url))
+    }
+
+    #[test]
+    fn converts_units_and_caches_the_station() {
+        let cache_dir = env::temp_dir().join("viktor-test-nws");
+        let _ = fs::remove_dir_all(&cache_dir);
+        let geocoding = r#"{"results": [{"name": "Washington", "latitude": 38.88944,
+                                         "longitude": -77.03524, "country": "United States"}]}"#;
+        // The fixtures link to each other, so they need the server address.
+        let (url, requests) = test_server::serve_with(|url| {
+            vec![fixture(include_str!("../fixtures/nws/points.json"), url),
+                 fixture(include_str!("../fixtures/nws/stations.json"), url),
+                 fixture(include_str!("../fixtures/nws/latest.json"), url),
+                 fixture(include_str!("../fixtures/nws/forecast_hourly.json"), url),
+                 test_server::ok(geocoding),
+                 fixture(include_str!("../fixtures/nws/latest.json"), url),
+                 fixture(include_str!("../fixtures/nws/forecast_hourly.json"), url)]
+        });
+        let provider = Nws::with_urls(&format!("{}/", url),
+                                      &format!("{}/", url),
+                                      "viktor-tests/0.1",
+                                      Some(cache_dir.clone()));
+        let client = Client::new();
+
+        let first = provider.current_weather(&client, &Query::Coords(38.88944, -77.03524)).unwrap();
+        // Cities are geocoded, and land on the station cached for their coordinates.
+        let second = provider.current_weather(&client, &Query::City("Washington".to_owned()))
+            .unwrap();
+
+        let points_request = requests.recv().unwrap();
+        assert!(points_request.starts_with("GET /points/38.8894,-77.0352 "));
+        assert!(points_request.contains("User-Agent: viktor-tests/0.1"));
+        assert!(requests.recv().unwrap().starts_with("GET /gridpoints/LWX/97,71/stations "));
+        assert!(requests.recv().unwrap().starts_with("GET /stations/KDCA/observations/latest "));
+        assert!(requests.recv().unwrap().starts_with("GET /gridpoints/LWX/97,71/forecast/hourly "));
+        assert!(requests.recv().unwrap().starts_with("GET /search?name=Washington"));
+        assert!(requests.recv().unwrap().starts_with("GET /stations/KDCA/observations/latest "));
+        for obs in vec![first, second] {
+            assert_eq!(obs.place.name, "Washington, DC");
+            assert_eq!(obs.temp_c, 7.2);
+            assert_eq!(obs.feelslike_c, 4.36);
+            assert!((obs.wind_kph - 16.668).abs() < 0.01);
+            assert_eq!(obs.humidity, Some(69));
+            assert_eq!(obs.condition, Condition::Rain);
+            assert_eq!(obs.elevation_m, Some(3.9624));
+            assert!(!obs.is_day);
+            // 10:52 UTC is 06:52 EDT, as the forecast periods tell
+            assert_eq!(obs.local_time, "2026-10-18 06:52");
+            assert_eq!(obs.place.utc_offset_s, Some(-14400));
+        }
+        let _ = fs::remove_dir_all(&cache_dir);
+    }
+
+    #[test]
+    fn reads_the_utc_offset_of_the_forecast_periods() {
+        assert_eq!(utc_offset("2016-12-15T09:00:00-05:00"), Some(-18000));
+        assert_eq!(utc_offset("2016-12-15T09:00:00+05:30"), Some(19800));
+        assert_eq!(utc_offset("2016-12-15T09:00:00Z"), Some(0));
+        assert_eq!(utc_offset("2016-12-15T09:00"), None);
+    }
+
+    #[test]
+    fn converts_quantitative_values() {
+        let temp = QuantitativeValue { unit_code: "wmoUnit:degF".to_owned(), value: Some(50.0) };
+        let wind = QuantitativeValue { unit_code: "wmoUnit:m_s-1".to_owned(), value: Some(5.0) };
+        let missing = QuantitativeValue { unit_code: "wmoUnit:degC".to_owned(), value: None };
+        assert_eq!(temp.celsius(), Some(10.0));
+        assert_eq!(wind.kph(), Some(18.0));
+        assert_eq!(missing.celsius(), None);
+        assert!((wind_kph("5 to 10 mph") - 16.09).abs() < 0.01);
+        assert_eq!(wind_kph("20 km/h"), 20.0);
+    }
+}
@@ -0,0 +1,374 @@
+extern crate serde_json;
+
+use weather::{fetch, Client, Condition, Observation, Place, Query, WeatherError, WeatherProvider};
+
+static OPEN_METEO_URL: &'static str = "https://api.open-meteo.com/v1/";
+pub static GEOCODING_URL: &'static str = "https://geocoding-api.open-meteo.com/v1/";
+static CURRENT_VARIABLES: &'static str = "temperature_2m,apparent_temperature,\
+                                          relative_humidity_2m,is_day,weather_code,\
+                                          cloud_cover,wind_speed_10m,precipitation,uv_index";
+static HOURLY_VARIABLES: &'static str = "temperature_2m,apparent_temperature,\
+                                         relative_humidity_2m,dew_point_2m,is_day,\
+                                         weather_code,cloud_cover,wind_speed_10m,\
+                                         wind_gusts_10m,visibility,precipitation,\
+                                         precipitation_probability,uv_index";
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Forecast {
+    /// Latitude of the grid cell, in decimal degree
+    latitude: f32,
+    /// Longitude of the grid cell, in decimal degree
+    longitude: f32,
+    /// Time zone name
+    timezone: String,
+    /// How far ahead of UTC the time zone is, in seconds
+    pub utc_offset_seconds: i64,
+    /// Elevation of the grid cell, in meters
+    pub elevation: Option<f32>,
+    pub current: Current,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Current {
+    /// Local time in ISO 8601, without seconds. e.g.: 2016-12-15T09:15
+    pub time: String,
+    /// Temperature at 2 meters, in celsius
+    pub temperature_2m: f32,
+    /// Feels like temperature, in celsius
+    pub apparent_temperature: f32,
+    /// Humidity at 2 meters, as percentage
+    pub relative_humidity_2m: u8,
+    pub is_day: u8, // 1 = Yes 0 = No
+    /// WMO weather interpretation code
+    pub weather_code: u32,
+    /// Cloud cover as percentage
+    pub cloud_cover: u8,
+    /// Wind speed at 10 meters, in kilometer per hour
+    pub wind_speed_10m: f32,
+    /// Precipitation amount of the preceding interval, in millimeters
+    precipitation: f32,
+    pub uv_index: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct HourlyForecast {
+    /// How far ahead of UTC the time zone is, in seconds
+    pub utc_offset_seconds: i64,
+    /// Elevation of the grid cell, in meters
+    pub elevation: Option<f32>,
+    pub hourly: Hourly,
+}
+
+/// The hourly forecast, one array per variable.
+#[derive(Deserialize, Debug, Default)]
+pub struct Hourly {
+    /// Local times in ISO 8601, without seconds. e.g.: 2016-12-15T09:00
+    pub time: Vec<String>,
+    pub temperature_2m: Vec<f32>,
+    pub apparent_temperature: Vec<f32>,
+    pub relative_humidity_2m: Vec<u8>,
+    pub dew_point_2m: Vec<Option<f32>>,
+    pub is_day: Vec<u8>,
+    pub weather_code: Vec<u32>,
+    pub cloud_cover: Vec<u8>,
+    pub wind_speed_10m: Vec<f32>,
+    pub wind_gusts_10m: Vec<Option<f32>>,
+    /// Visibility in meters
+    pub visibility: Vec<Option<f32>>,
+    /// Precipitation amount of the preceding hour, in millimeters
+    precipitation: Vec<f32>,
+    /// Probability of precipitation as percentage
+    #[serde(default)]
+    pub precipitation_probability: Vec<Option<f32>>,
+    #[serde(default)]
+    pub uv_index: Vec<Option<f32>>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+struct GeocodingResults {
+    results: Option<Vec<GeocodedPlace>>,
+}
+
+/// A place with coordinates, as returned by the Open-Meteo geocoding API.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct GeocodedPlace {
+    pub name: String,
+    pub latitude: f32,
+    pub longitude: f32,
+    pub country: Option<String>,
+}
+
+impl GeocodedPlace {
+    pub fn to_place(&self) -> Place {
+        Place {
+            name: self.name.clone(),
+            country: self.country.clone().unwrap_or(String::new()),
+            utc_offset_s: None,
+        }
+    }
+}
+
+/// The Open-Meteo forecast API, which doesn't need an API key.
+pub struct OpenMeteo {
+    base_url: String,
+    geocoding_url: String,
+}
+
+impl OpenMeteo {
+    pub fn new() -> OpenMeteo {
+        OpenMeteo::with_urls(OPEN_METEO_URL, GEOCODING_URL)
+    }
+
+    pub fn with_urls(base_url: &str, geocoding_url: &str) -> OpenMeteo {
+        OpenMeteo {
+            base_url: base_url.to_owned(),
+            geocoding_url: geocoding_url.to_owned(),
+        }
+    }
+}
+
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "open-meteo"
+    }
+
+    fn current_weather(&self, client: &Client, query: &Query) -> Result<Observation, WeatherError> {
+        let place = try!(geocode(client, &self.geocoding_url, self.name(), query));
+        let url = format!("{}forecast?latitude={}&longitude={}&current={}&wind_speed_unit=kmh&timezone=auto",
+                          self.base_url,
+                          place.latitude,
+                          place.longitude,
+                          CURRENT_VARIABLES);
+        let body = try!(fetch(client, &url));
+        let forecast: Forecast = try!(serde_json::from_str(&body));
+        let mut obs = to_observation(&place, &forecast.current);
+        obs.elevation_m = forecast.elevation;
+        obs.place.utc_offset_s = Some(forecast.utc_offset_seconds);
+        Ok(obs)
+    }
+
+    fn hourly_forecast(&self,
+                       client: &Client,
+                       query: &Query)
+                       -> Result<Vec<Observation>, WeatherError> {
+        let place = try!(geocode(client, &self.geocoding_url, self.name(), query));
+        let url = format!("{}forecast?latitude={}&longitude={}&hourly={}&wind_speed_unit=kmh&timezone=auto\
+                           &forecast_days=3",
+                          self.base_url,
+                          place.latitude,
+                          place.longitude,
+                          HOURLY_VARIABLES);
+        let body = try!(fetch(client, &url));
+        let forecast: HourlyForecast = try!(serde_json::from_str(&body));
+        Ok(to_forecast(&place, &forecast.hourly)
+            .into_iter()
+            .map(|mut o| {
+                o.elevation_m = forecast.elevation;
+                o.place.utc_offset_s = Some(forecast.utc_offset_seconds);
+                o
+            })
+            .collect())
+    }
+}
+
+/// Resolves the query into coordinates, looking up city names via the
+/// Open-Meteo geocoding API. Useful for all the providers which only
+/// understand latitude and longitude.
+pub fn geocode(client: &Client,
+               geocoding_url: &str,
+               provider: &'static str,
+               query: &Query)
+               -> Result<GeocodedPlace, WeatherError> {
+    match *query {
+        Query::AutoIp => Err(WeatherError::MissingLocation(provider)),
+        Query::Coords(lat, lon) => {
+            Ok(GeocodedPlace {
+                name: format!("{:.2}, {:.2}", lat, lon),
+                latitude: lat,
+                longitude: lon,
+                country: None,
+            })
+        }
+        Query::City(ref city) => {
+            let url = format!("{}search?name={}&count=1&format=json", geocoding_url, city);
+            let body = try!(fetch(client, &url));
+            let found: GeocodingResults = try!(serde_json::from_str(&body));
+            found.results
+                .and_then(|r| r.into_iter().next())
+                .ok_or(WeatherError::LocationNotFound(city.clone()))
+        }
+    }
+}
+
+pub fn to_observation(place: &GeocodedPlace, current: &Current) -> Observation {
+    Observation {
+        place: place.to_place(),
+        local_time: current.time.replace("T", " "),
+        temp_c: current.temperature_2m,
+        feelslike_c: current.apparent_temperature,
+        wind_kph: current.wind_speed_10m,
+        gust_kph: None,
+        visibility_km: None,
+        dew_point_c: None,
+        humidity: Some(current.relative_humidity_2m),
+        cloud: Some(current.cloud_cover),
+        precip_mm: Some(current.precipitation),
+        precip_chance: None,
+        uv_index: current.uv_index,
+        elevation_m: None,
+        is_day: current.is_day != 0,
+        condition: to_condition(current.weather_code),
+        condition_text: describe(current.weather_code).to_owned(),
+    }
+}
+
+pub fn to_forecast(place: &GeocodedPlace, hourly: &Hourly) -> Vec<Observation> {
+    let nth = |values: &Vec<Option<f32>>, i: usize| values.get(i).and_then(|v| *v);
+    (0..hourly.time.len())
+        .filter(|&i| {
+            i < hourly.temperature_2m.len() && i < hourly.apparent_temperature.len() &&
+            i < hourly.relative_humidity_2m.len() && i < hourly.is_day.len() &&
+            i < hourly.weather_code.len() && i < hourly.cloud_cover.len() &&
+            i < hourly.wind_speed_10m.len()
+        })
+        .map(|i| {
+            Observation {
+                place: place.to_place(),
+                local_time: hourly.time[i].replace("T", " "),
+                temp_c: hourly.temperature_2m[i],
+                feelslike_c: hourly.apparent_temperature[i],
+                wind_kph: hourly.wind_speed_10m[i],
+                gust_kph: nth(&hourly.wind_gusts_10m, i),
+                visibility_km: nth(&hourly.visibility, i).map(|m| m / 1000.0),
+                dew_point_c: nth(&hourly.dew_point_2m, i),
+                humidity: Some(hourly.relative_humidity_2m[i]),
+                cloud: Some(hourly.cloud_cover[i]),
+                precip_mm: hourly.precipitation.get(i).map(|p| *p),
+                precip_chance: nth(&hourly.precipitation_probability, i).map(|p| p.round() as u8),
+                uv_index: nth(&hourly.uv_index, i),
+                elevation_m: None,
+                is_day: hourly.is_day[i] != 0,
+                condition: to_condition(hourly.weather_code[i]),
+                condition_text: describe(hourly.weather_code[i]).to_owned(),
+            }
+        })
+        .collect()
+}
+
+// WMO weather interpretation codes, as documented on https://open-meteo.com/en/docs
+pub fn to_condition(wmo_code: u32) -> Condition {
+    match wmo_code {
+        0 | 1 => Condition::Clear,
+        2 => Condition::PartlyCloudy,
+        3 => Condition::Overcast,
+        45 | 48 => Condition::Fog,
+        51 | 53 | 55 => Condition::Drizzle,
+        61 | 80 => Condition::LightRain,
+        63 | 81 => Condition::Rain,
+        65 | 82 => Condition::HeavyRain,
+        56 | 57 | 66 | 67 => Condition::Sleet,
+        95 | 96 | 99 => Condition::Thunderstorm,
+        // Assume snow otherwise (71-77, 85, 86)
+        _ => Condition::Snow,
+    }
+}
+
+pub fn describe(wmo_code: u32) -> &'static str {
+    match wmo_code {
+        0 => "Clear sky",
+        1 => "Mainly clear",
+        2 => "Partly cloudy",
+        3 => "Overcast",
+        45 => "Fog",
+        48 => "Depositing rime fog",
+        51 => "Light drizzle",
+        53 => "Moderate drizzle",
+        55 => "Dense drizzle",
+        56 => "Light freezing drizzle",
+        57 => "Dense freezing drizzle",
+        61 => "Slight rain",
+        63 => "Moderate rain",
+        65 => "Heavy rain",
+        66 => "Light freezing rain",
+        67 => "Heavy freezing rain",
+        71 => "Slight snow fall",
+        73 => "Moderate snow fall",
+        75 => "Heavy snow fall",
+        77 => "Snow grains",
+        80 => "Slight rain showers",
+        81 => "Moderate rain showers",
+        82 => "Violent rain showers",
+        85 => "Slight snow showers",
+        86 => "Heavy snow showers",
+        95 => "Thunderstorm",
+        96 => "Thunderstorm with slight hail",
+        99 => "Thunderstorm with heavy hail",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_server;
+    use weather::{Client, Condition, Query, WeatherError, WeatherProvider};
+
+    #[test]
+    fn can_decode_a_recorded_current_weather() {
+        let (url, requests) = test_server::serve(vec![
+            test_server::ok(include_str!("../fixtures/open_meteo/geocoding.json")),
+            test_server::ok(include_str!("../fixtures/open_meteo/current.json")),
+        ]);
+        let provider = OpenMeteo::with_urls(&format!("{}/v1/", url), &format!("{}/v1/", url));
+        let obs = provider.current_weather(&Client::new(), &Query::City("Marsala".to_owned()))
+            .unwrap();
+
+        assert!(requests.recv().unwrap().starts_with("GET /v1/search?name=Marsala"));
+        assert!(requests.recv().unwrap().contains("latitude=37.79664"));
+        assert_eq!(obs.place.name, "Marsala");
+        assert_eq!(obs.place.country, "Italy");
+        assert_eq!(obs.local_time, "2026-10-18 10:45");
+        assert_eq!(obs.temp_c, 18.3);
+        assert_eq!(obs.humidity, Some(68));
+        assert_eq!(obs.condition, Condition::LightRain);
+        assert_eq!(obs.elevation_m, Some(12.0));
+        assert!(obs.is_day);
+    }
+
+    #[test]
+    fn can_decode_a_recorded_hourly_forecast() {
+        let (url, requests) = test_server::serve(vec![
+            test_server::ok(include_str!("../fixtures/open_meteo/hourly.json")),
+        ]);
+        let provider = OpenMeteo::with_urls(&format!("{}/v1/", url), &format!("{}/v1/", url));
+        let forecast = provider.hourly_forecast(&Client::new(), &Query::Coords(37.79664, 12.43518))
+            .unwrap();
+
+        assert!(requests.recv().unwrap().contains("&hourly=temperature_2m,"));
+        assert_eq!(forecast.len(), 3);
+        assert_eq!(forecast[0].local_time, "2026-10-19 05:00");
+        assert_eq!(forecast[2].temp_c, 12.6);
+        assert_eq!(forecast[2].gust_kph, Some(24.8));
+        assert_eq!(forecast[2].visibility_km, None);
+        assert_eq!(forecast[1].condition, Condition::LightRain);
+        assert_eq!(forecast[1].elevation_m, Some(12.0));
+        assert!(forecast[2].is_day);
+    }
+
+    #[test]
+    fn needs_an_explicit_location() {
+        match OpenMeteo::new().current_weather(&Client::new(), &Query::AutoIp) {
+            Err(WeatherError::MissingLocation(p)) => assert_eq!(p, "open-meteo"),
+            _ => panic!("Expected a MissingLocation error"),
+        }
+    }
+
+    #[test]
+    fn can_map_wmo_codes() {
+        assert_eq!(to_condition(0), Condition::Clear);
+        assert_eq!(to_condition(45), Condition::Fog);
+        assert_eq!(to_condition(65), Condition::HeavyRain);
+        assert_eq!(to_condition(73), Condition::Snow);
+        assert_eq!(to_condition(95), Condition::Thunderstorm);
+    }
+}
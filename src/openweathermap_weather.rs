@@ -0,0 +1,363 @@
+extern crate serde_json;
+
+use std::env;
+
+use weather::{fetch, local_time, Client, Condition, Observation, Place, Query, WeatherError,
+              WeatherProvider};
+
+static OPENWEATHERMAP_URL: &'static str = "https://api.openweathermap.org/data/2.5/";
+
+/// The unit system OpenWeatherMap reports its values in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Units {
+    /// Kelvin and meters per second
+    Standard,
+    /// Celsius and meters per second
+    Metric,
+    /// Fahrenheit and miles per hour
+    Imperial,
+}
+
+impl Units {
+    pub fn parse(input: &str) -> Result<Units, WeatherError> {
+        match input {
+            "standard" => Ok(Units::Standard),
+            "metric" => Ok(Units::Metric),
+            "imperial" => Ok(Units::Imperial),
+            _ => Err(WeatherError::InvalidSetting(format!("openweathermap_units: {}", input))),
+        }
+    }
+
+    fn as_param(&self) -> &'static str {
+        match *self {
+            Units::Standard => "standard",
+            Units::Metric => "metric",
+            Units::Imperial => "imperial",
+        }
+    }
+
+    pub fn to_celsius(&self, temp: f32) -> f32 {
+        match *self {
+            Units::Standard => temp - 273.15,
+            Units::Metric => temp,
+            Units::Imperial => (temp - 32.0) * 5.0 / 9.0,
+        }
+    }
+
+    pub fn to_kph(&self, speed: f32) -> f32 {
+        match *self {
+            Units::Standard | Units::Metric => speed * 3.6,
+            Units::Imperial => speed * 1.609344,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct CurrentWeather {
+    pub weather: Vec<WeatherCondition>,
+    pub main: Main,
+    pub wind: Wind,
+    pub clouds: Clouds,
+    /// Visibility in meters, always metric
+    pub visibility: Option<f32>,
+    pub rain: Option<Precipitation>,
+    pub snow: Option<Precipitation>,
+    /// Time of the observation, in unix time (UTC)
+    pub dt: i64,
+    pub sys: Sys,
+    /// Shift in seconds from UTC
+    pub timezone: i64,
+    /// City name
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct WeatherCondition {
+    /// OpenWeatherMap condition id
+    pub id: u32,
+    pub description: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Main {
+    pub temp: f32,
+    pub feels_like: f32,
+    /// Humidity as percentage
+    pub humidity: u8,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Wind {
+    pub speed: f32,
+    /// Wind direction in degrees
+    deg: Option<i32>,
+    pub gust: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Clouds {
+    /// Cloud cover as percentage
+    pub all: u8,
+}
+
+/// Precipitation volume in millimeters, always metric. Only present when
+/// there is any.
+#[derive(Deserialize, Debug, Default)]
+pub struct Precipitation {
+    /// Over the last hour
+    #[serde(rename="1h")]
+    pub one_hour: Option<f32>,
+    /// Over the last (or, in a forecast, next) three hours
+    #[serde(rename="3h")]
+    pub three_hours: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Sys {
+    /// Country code, e.g.: IT
+    pub country: Option<String>,
+    /// Sunrise time, in unix time (UTC)
+    pub sunrise: i64,
+    /// Sunset time, in unix time (UTC)
+    pub sunset: i64,
+}
+
+/// The 5 day forecast, in 3 hour slots.
+#[derive(Deserialize, Debug, Default)]
+pub struct Forecast {
+    pub list: Vec<ForecastSlot>,
+    pub city: City,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct ForecastSlot {
+    /// Start of the slot, in unix time (UTC)
+    pub dt: i64,
+    pub main: Main,
+    pub weather: Vec<WeatherCondition>,
+    pub clouds: Clouds,
+    pub wind: Wind,
+    /// Visibility in meters, always metric
+    pub visibility: Option<f32>,
+    pub rain: Option<Precipitation>,
+    pub snow: Option<Precipitation>,
+    /// Probability of precipitation, from 0 to 1
+    pub pop: Option<f32>,
+    pub sys: SlotSys,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct SlotSys {
+    /// Part of the day, "d" or "n"
+    pub pod: String,
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct City {
+    pub name: String,
+    pub country: Option<String>,
+    /// Shift in seconds from UTC
+    pub timezone: i64,
+}
+
+/// The OpenWeatherMap "current weather" and "5 day forecast" APIs.
+pub struct OpenWeatherMap {
+    base_url: String,
+    api_key: String,
+    units: Units,
+}
+
+impl OpenWeatherMap {
+    /// Builds the provider out of the given key, falling back to the
+    /// OPENWEATHERMAP_API_KEY env var.
+    pub fn new(api_key: &Option<String>, units: Units) -> Result<OpenWeatherMap, WeatherError> {
+        let key = api_key.clone().or(env::var("OPENWEATHERMAP_API_KEY").ok());
+        match key {
+            None => Err(WeatherError::MissingApiKey("OPENWEATHERMAP_API_KEY")),
+            Some(k) => Ok(OpenWeatherMap::with_url(OPENWEATHERMAP_URL, &k, units)),
+        }
+    }
+
+    pub fn with_url(base_url: &str, api_key: &str, units: Units) -> OpenWeatherMap {
+        OpenWeatherMap {
+            base_url: base_url.to_owned(),
+            api_key: api_key.to_owned(),
+            units: units,
+        }
+    }
+
+    fn location(&self, query: &Query) -> Result<String, WeatherError> {
+        match *query {
+            Query::AutoIp => Err(WeatherError::MissingLocation(self.name())),
+            Query::City(ref c) => Ok(format!("q={}", c)),
+            Query::Coords(lat, lon) => Ok(format!("lat={}&lon={}", lat, lon)),
+        }
+    }
+
+    fn url(&self, endpoint: &str, location: &str) -> String {
+        format!("{}{}?{}&units={}&appid={}",
+                self.base_url,
+                endpoint,
+                location,
+                self.units.as_param(),
+                self.api_key)
+    }
+}
+
+impl WeatherProvider for OpenWeatherMap {
+    fn name(&self) -> &'static str {
+        "openweathermap"
+    }
+
+    fn current_weather(&self, client: &Client, query: &Query) -> Result<Observation, WeatherError> {
+        let location = try!(self.location(query));
+        let body = try!(fetch(client, &self.url("weather", &location)));
+        let cw: CurrentWeather = try!(serde_json::from_str(&body));
+        Ok(to_observation(&cw, self.units))
+    }
+
+    fn hourly_forecast(&self,
+                       client: &Client,
+                       query: &Query)
+                       -> Result<Vec<Observation>, WeatherError> {
+        let location = try!(self.location(query));
+        let body = try!(fetch(client, &self.url("forecast", &location)));
+        let forecast: Forecast = try!(serde_json::from_str(&body));
+        Ok(to_forecast(&forecast, self.units))
+    }
+}
+
+pub fn to_observation(cw: &CurrentWeather, units: Units) -> Observation {
+    let condition_id = cw.weather.first().map(|c| c.id).unwrap_or(800);
+    Observation {
+        place: Place {
+            name: cw.name.clone(),
+            country: cw.sys.country.clone().unwrap_or(String::new()),
+            utc_offset_s: Some(cw.timezone),
+        },
+        local_time: local_time(cw.dt, cw.timezone),
+        temp_c: units.to_celsius(cw.main.temp),
+        feelslike_c: units.to_celsius(cw.main.feels_like),
+        wind_kph: units.to_kph(cw.wind.speed),
+        gust_kph: cw.wind.gust.map(|g| units.to_kph(g)),
+        visibility_km: cw.visibility.map(|v| v / 1000.0),
+        dew_point_c: None,
+        humidity: Some(cw.main.humidity),
+        cloud: Some(cw.clouds.all),
+        precip_mm: precipitation(&cw.rain, &cw.snow),
+        precip_chance: None,
+        uv_index: None,
+        elevation_m: None,
+        is_day: cw.dt >= cw.sys.sunrise && cw.dt < cw.sys.sunset,
+        condition: to_condition(condition_id),
+        condition_text: cw.weather.first().map(|c| c.description.clone()).unwrap_or(String::new()),
+    }
+}
+
+pub fn to_forecast(forecast: &Forecast, units: Units) -> Vec<Observation> {
+    let place = Place {
+        name: forecast.city.name.clone(),
+        country: forecast.city.country.clone().unwrap_or(String::new()),
+        utc_offset_s: Some(forecast.city.timezone),
+    };
+    forecast.list
+        .iter()
+        .map(|slot| {
+            let condition_id = slot.weather.first().map(|c| c.id).unwrap_or(800);
+            Observation {
+                place: place.clone(),
+                local_time: local_time(slot.dt, forecast.city.timezone),
+                temp_c: units.to_celsius(slot.main.temp),
+                feelslike_c: units.to_celsius(slot.main.feels_like),
+                wind_kph: units.to_kph(slot.wind.speed),
+                gust_kph: slot.wind.gust.map(|g| units.to_kph(g)),
+                visibility_km: slot.visibility.map(|v| v / 1000.0),
+                dew_point_c: None,
+                humidity: Some(slot.main.humidity),
+                cloud: Some(slot.clouds.all),
+                precip_mm: precipitation(&slot.rain, &slot.snow),
+                precip_chance: slot.pop.map(|p| (p * 100.0).round() as u8),
+                uv_index: None,
+                elevation_m: None,
+                is_day: slot.sys.pod == "d",
+                condition: to_condition(condition_id),
+                condition_text: slot.weather
+                    .first()
+                    .map(|c| c.description.clone())
+                    .unwrap_or(String::new()),
+            }
+        })
+        .collect()
+}
+
+/// Rain and snow together, in millimeters per hour.
+fn precipitation(rain: &Option<Precipitation>, snow: &Option<Precipitation>) -> Option<f32> {
+    let per_hour = |p: &Option<Precipitation>| {
+        p.as_ref().and_then(|p| p.one_hour.or(p.three_hours.map(|v| v / 3.0)))
+    };
+    Some(per_hour(rain).unwrap_or(0.0) + per_hour(snow).unwrap_or(0.0))
+}
+
+// See https://openweathermap.org/weather-conditions
+pub fn to_condition(id: u32) -> Condition {
+    match id {
+        200...299 => Condition::Thunderstorm,
+        300...399 => Condition::Drizzle,
+        500 | 520 => Condition::LightRain,
+        501 | 521 => Condition::Rain,
+        502...504 | 522 | 531 => Condition::HeavyRain,
+        511 | 611...616 => Condition::Sleet,
+        600...699 => Condition::Snow,
+        700...799 => Condition::Fog,
+        800 => Condition::Clear,
+        801 | 802 => Condition::PartlyCloudy,
+        803 | 804 => Condition::Overcast,
+        // Assume rain otherwise
+        _ => Condition::Rain,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_server;
+    use weather::{Client, Condition, Observation, Query, WeatherProvider};
+
+    fn observe(units: Units, fixture: &str) -> Observation {
+        let (url, requests) = test_server::serve(vec![test_server::ok(fixture)]);
+        let provider = OpenWeatherMap::with_url(&format!("{}/", url), "xxxxxx", units);
+        let obs = provider.current_weather(&Client::new(), &Query::City("Marsala".to_owned()))
+            .unwrap();
+        let request = requests.recv().unwrap();
+        assert!(request.contains(&format!("units={}", units.as_param())));
+        obs
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 0.1, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn parses_all_unit_systems_the_same_way() {
+        let fixtures = vec![(Units::Standard, include_str!("../fixtures/openweathermap/standard.json")),
+                            (Units::Metric, include_str!("../fixtures/openweathermap/metric.json")),
+                            (Units::Imperial, include_str!("../fixtures/openweathermap/imperial.json"))];
+        for (units, fixture) in fixtures {
+            let obs = observe(units, fixture);
+            assert_close(obs.temp_c, 18.3);
+            assert_close(obs.feelslike_c, 18.1);
+            assert_close(obs.wind_kph, 12.6);
+            assert_eq!(obs.humidity, Some(68));
+            assert_eq!(obs.cloud, Some(75));
+            assert_eq!(obs.condition, Condition::LightRain);
+            assert_eq!(obs.local_time, "2026-10-18 12:45");
+            assert!(obs.is_day);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_units() {
+        assert!(Units::parse("kelvin").is_err());
+        assert_eq!(Units::parse("imperial").unwrap(), Units::Imperial);
+    }
+}
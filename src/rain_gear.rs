@@ -0,0 +1,119 @@
+//! What to wear against the rain, out of how much (and how likely) it rains.
+
+use std::cmp;
+use std::fmt;
+
+use weather::{Condition, Observation};
+
+pub static RAIN_GEAR: [&'static str; 4] = ["none", "shell", "waterproof", "socks"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RainGear {
+    /// It's not going to rain, or hardly
+    NoRainGear,
+    /// Drizzle, or a shower which may not come: a water-resistant shell
+    Shell,
+    /// Proper rain: a waterproof jacket and a cap
+    Waterproof,
+    /// A downpour: you'll be soaked anyway, keep dry socks for after the run
+    ChangeOfSocks,
+}
+
+impl RainGear {
+    /// Out of the precipitation amount, in millimeters.
+    pub fn from_amount(precip_mm: f32) -> RainGear {
+        if precip_mm < 0.1 {
+            return RainGear::NoRainGear;
+        }
+        if precip_mm < 1.0 {
+            return RainGear::Shell;
+        }
+        if precip_mm < 4.0 {
+            return RainGear::Waterproof;
+        }
+        RainGear::ChangeOfSocks
+    }
+
+    /// Out of the condition alone, when the provider tells no amount.
+    pub fn from_condition(condition: Condition) -> RainGear {
+        match condition {
+            Condition::Drizzle | Condition::LightRain => RainGear::Shell,
+            Condition::Rain | Condition::Sleet | Condition::Snow => RainGear::Waterproof,
+            Condition::HeavyRain | Condition::Thunderstorm => RainGear::ChangeOfSocks,
+            Condition::Clear | Condition::PartlyCloudy | Condition::Overcast | Condition::Fog => {
+                RainGear::NoRainGear
+            }
+        }
+    }
+
+    /// The gear for the given weather: the amount of precipitation (or the
+    /// condition, if it's not known) tells how much it rains, and the
+    /// probability how much to trust it.
+    pub fn of(weather: &Observation) -> RainGear {
+        let expected = match weather.precip_mm {
+            Some(mm) if mm > 0.0 => RainGear::from_amount(mm),
+            _ => RainGear::from_condition(weather.condition),
+        };
+        match weather.precip_chance {
+            Some(c) if c < 30 => RainGear::NoRainGear,
+            Some(c) if c < 60 => {
+                cmp::max(RainGear::Shell, cmp::min(expected, RainGear::Waterproof))
+            }
+            Some(_) => cmp::max(RainGear::Shell, expected),
+            None => expected,
+        }
+    }
+
+    /// The name used in the clothing rules, e.g. "waterproof".
+    pub fn name(&self) -> &'static str {
+        RAIN_GEAR[*self as usize]
+    }
+}
+
+impl fmt::Display for RainGear {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RainGear::NoRainGear => write!(f, "no rain gear"),
+            RainGear::Shell => write!(f, "a water-resistant shell"),
+            RainGear::Waterproof => write!(f, "a waterproof jacket and a cap"),
+            RainGear::ChangeOfSocks => write!(f, "waterproofs, and a change of socks"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weather::{Condition, Observation};
+
+    fn weather(condition: Condition, precip_mm: Option<f32>, precip_chance: Option<u8>) -> Observation {
+        Observation {
+            condition: condition,
+            precip_mm: precip_mm,
+            precip_chance: precip_chance,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn drizzle_and_downpours_call_for_different_gear() {
+        assert_eq!(RainGear::of(&weather(Condition::Rain, Some(0.4), None)), RainGear::Shell);
+        assert_eq!(RainGear::of(&weather(Condition::Rain, Some(6.0), None)),
+                   RainGear::ChangeOfSocks);
+        assert_eq!(RainGear::of(&weather(Condition::Rain, None, None)), RainGear::Waterproof);
+        assert_eq!(RainGear::of(&weather(Condition::Clear, Some(0.0), None)),
+                   RainGear::NoRainGear);
+    }
+
+    #[test]
+    fn the_chance_of_rain_tempers_the_gear() {
+        assert_eq!(RainGear::of(&weather(Condition::Rain, Some(2.0), Some(20))),
+                   RainGear::NoRainGear);
+        assert_eq!(RainGear::of(&weather(Condition::HeavyRain, Some(8.0), Some(50))),
+                   RainGear::Waterproof);
+        assert_eq!(RainGear::of(&weather(Condition::Overcast, Some(0.0), Some(40))),
+                   RainGear::Shell);
+        assert_eq!(RainGear::of(&weather(Condition::HeavyRain, Some(8.0), Some(90))),
+                   RainGear::ChangeOfSocks);
+    }
+}
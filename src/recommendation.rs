@@ -0,0 +1,94 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, Hash, Ord, Eq, PartialOrd, PartialEq)]
+pub enum BodyPart {
+    Head,
+    Torso,
+    Hands,
+    Legs,
+    Feet,
+}
+
+impl BodyPart {
+    pub fn parse(input: &str) -> Option<BodyPart> {
+        match input {
+            "head" => Some(BodyPart::Head),
+            "torso" => Some(BodyPart::Torso),
+            "hands" => Some(BodyPart::Hands),
+            "legs" => Some(BodyPart::Legs),
+            "feet" => Some(BodyPart::Feet),
+            _ => None,
+        }
+    }
+
+    /// The name used in the rules file and on the command line, e.g. "hands".
+    pub fn name(&self) -> &'static str {
+        match *self {
+            BodyPart::Head => "head",
+            BodyPart::Torso => "torso",
+            BodyPart::Hands => "hands",
+            BodyPart::Legs => "legs",
+            BodyPart::Feet => "feet",
+        }
+    }
+}
+
+impl fmt::Display for BodyPart {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            BodyPart::Head => write!(f, "Head"),
+            BodyPart::Torso => write!(f, "Torso"),
+            BodyPart::Hands => write!(f, "Hands"),
+            BodyPart::Legs => write!(f, "Legs"),
+            BodyPart::Feet => write!(f, "Feet"),
+        }
+    }
+}
+
+/// A piece of clothing (or gear) to wear.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Garment {
+    pub body_part: BodyPart,
+    /// e.g. "Tights"
+    pub name: String,
+    /// Why, or how, to wear it
+    pub description: String,
+    /// A picture of it, if the recommendation comes with one
+    pub image: Option<Picture>,
+}
+
+/// A picture of a garment, and how big to show it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Picture {
+    pub url: String,
+    pub width: TermDimension,
+    pub height: TermDimension,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum TermDimension {
+    Auto,
+    Dimension(u8),
+}
+
+impl fmt::Display for TermDimension {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TermDimension::Auto => write!(f, "auto"),
+            TermDimension::Dimension(d) => write!(f, "{}", d),
+        }
+    }
+}
+
+/// What to wear, from head to toe.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recommendation {
+    pub items: Vec<Garment>,
+}
+
+impl Recommendation {
+    pub fn new(mut items: Vec<Garment>) -> Recommendation {
+        items.sort_by(|a, b| a.body_part.cmp(&b.body_part));
+        Recommendation { items: items }
+    }
+}
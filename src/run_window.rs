@@ -0,0 +1,214 @@
+use std::cmp;
+
+use weather::{local_minutes, timed_slots, Condition, Observation, WeatherError};
+
+/// The weather over the whole run, out of the hourly forecast.
+#[derive(Debug, Clone)]
+pub struct RunWindow {
+    /// Local start of the run, as `YYYY-MM-DD HH:MM`
+    pub start: String,
+    pub duration_minutes: i64,
+    /// The forecast slots the run spans
+    pub slots: Vec<Observation>,
+}
+
+impl RunWindow {
+    /// Picks the slots a run of `duration_minutes` starting at `start`
+    /// spans. The part of the run the forecast doesn't reach is ignored.
+    pub fn new(forecast: &[Observation],
+               start: &str,
+               duration_minutes: i64)
+               -> Result<RunWindow, WeatherError> {
+        let out_of_range = || WeatherError::ForecastOutOfRange(start.to_owned());
+        let at = try!(local_minutes(start).ok_or(out_of_range()));
+        let end = at + cmp::max(duration_minutes, 1);
+        let (slots, slot_length) = timed_slots(forecast);
+        let spanned: Vec<Observation> = slots.iter()
+            .filter(|&&(m, _)| m + slot_length > at && m < end)
+            .map(|&(_, o)| o.clone())
+            .collect();
+        if spanned.is_empty() {
+            return Err(out_of_range());
+        }
+        Ok(RunWindow {
+            start: start.to_owned(),
+            duration_minutes: duration_minutes,
+            slots: spanned,
+        })
+    }
+
+    pub fn min_temp_c(&self) -> f32 {
+        self.slots.iter().map(|o| o.temp_c).fold(::std::f32::INFINITY, f32::min)
+    }
+
+    pub fn max_temp_c(&self) -> f32 {
+        self.slots.iter().map(|o| o.temp_c).fold(::std::f32::NEG_INFINITY, f32::max)
+    }
+
+    pub fn min_feelslike_c(&self) -> f32 {
+        self.slots.iter().map(|o| o.feelslike_c).fold(::std::f32::INFINITY, f32::min)
+    }
+
+    pub fn max_feelslike_c(&self) -> f32 {
+        self.slots.iter().map(|o| o.feelslike_c).fold(::std::f32::NEG_INFINITY, f32::max)
+    }
+
+    pub fn max_wind_kph(&self) -> f32 {
+        self.slots.iter().map(|o| o.wind_kph).fold(0.0, f32::max)
+    }
+
+    pub fn max_gust_kph(&self) -> Option<f32> {
+        self.slots
+            .iter()
+            .filter_map(|o| o.gust_kph)
+            .fold(None, |acc, g| Some(acc.map_or(g, |a: f32| a.max(g))))
+    }
+
+    /// The heaviest hourly precipitation over the run, if the provider
+    /// reports it. Not the total: the rain gear goes by the rate.
+    pub fn max_precip_mm(&self) -> Option<f32> {
+        self.slots
+            .iter()
+            .filter_map(|o| o.precip_mm)
+            .fold(None, |acc, p| Some(acc.map_or(p, |a: f32| a.max(p))))
+    }
+
+    /// The highest probability of precipitation over the run, if forecast.
+    pub fn precip_chance(&self) -> Option<u8> {
+        self.slots.iter().filter_map(|o| o.precip_chance).max()
+    }
+
+    /// The highest UV index over the run, if the provider reports it.
+    pub fn max_uv_index(&self) -> Option<f32> {
+        self.slots
+            .iter()
+            .filter_map(|o| o.uv_index)
+            .fold(None, |acc, u| Some(acc.map_or(u, |a: f32| a.max(u))))
+    }
+
+    /// Whether it's going to rain (or snow) at any point of the run.
+    pub fn is_wet(&self) -> bool {
+        self.slots.iter().any(|o| o.condition.is_precipitation()) ||
+        self.max_precip_mm().map(|p| p > 0.0).unwrap_or(false)
+    }
+
+    /// The slot with the nastiest weather.
+    fn worst_slot(&self) -> &Observation {
+        let mut worst = &self.slots[0];
+        for slot in self.slots.iter() {
+            if severity(slot.condition) > severity(worst.condition) {
+                worst = slot;
+            }
+        }
+        worst
+    }
+
+    /// A single observation summing up the run, to dress for: the coldest
+    /// temperature, the strongest wind and the worst weather of the window.
+    pub fn to_observation(&self) -> Observation {
+        self.summary(self.min_temp_c(), self.min_feelslike_c())
+    }
+
+    /// The same as `to_observation`, but at the warmest temperature of the
+    /// window: what the layers worn at the start must be fit for.
+    pub fn warmest_observation(&self) -> Observation {
+        self.summary(self.max_temp_c(), self.max_feelslike_c())
+    }
+
+    fn summary(&self, temp_c: f32, feelslike_c: f32) -> Observation {
+        let worst = self.worst_slot();
+        let mut obs = self.slots[0].clone();
+        obs.local_time = self.start.clone();
+        obs.temp_c = temp_c;
+        obs.feelslike_c = feelslike_c;
+        obs.wind_kph = self.max_wind_kph();
+        obs.gust_kph = self.max_gust_kph();
+        obs.humidity = self.slots.iter().filter_map(|o| o.humidity).max();
+        obs.dew_point_c = None;
+        obs.precip_mm = self.max_precip_mm();
+        obs.precip_chance = self.precip_chance();
+        obs.uv_index = self.max_uv_index();
+        obs.condition = worst.condition;
+        obs.condition_text = worst.condition_text.clone();
+        obs
+    }
+}
+
+fn severity(condition: Condition) -> u8 {
+    match condition {
+        Condition::Clear => 0,
+        Condition::PartlyCloudy => 1,
+        Condition::Overcast => 2,
+        Condition::Fog => 3,
+        Condition::Drizzle => 4,
+        Condition::LightRain => 5,
+        Condition::Rain => 6,
+        Condition::HeavyRain => 7,
+        Condition::Sleet => 8,
+        Condition::Snow => 9,
+        Condition::Thunderstorm => 10,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weather::{Condition, Observation};
+
+    fn slot(local_time: &str, temp_c: f32, wind_kph: f32, condition: Condition) -> Observation {
+        Observation {
+            local_time: local_time.to_owned(),
+            temp_c: temp_c,
+            feelslike_c: temp_c,
+            wind_kph: wind_kph,
+            condition: condition,
+            ..Default::default()
+        }
+    }
+
+    fn dawn() -> Vec<Observation> {
+        vec![slot("2026-10-19 05:00", 4.0, 5.0, Condition::Clear),
+             slot("2026-10-19 06:00", 7.0, 12.0, Condition::PartlyCloudy),
+             slot("2026-10-19 07:00", 11.0, 20.0, Condition::LightRain),
+             slot("2026-10-19 08:00", 14.0, 8.0, Condition::Overcast),
+             slot("2026-10-19 09:00", 16.0, 8.0, Condition::HeavyRain)]
+    }
+
+    #[test]
+    fn spans_every_slot_of_the_run() {
+        let window = RunWindow::new(&dawn(), "2026-10-19 05:30", 180).unwrap();
+        assert_eq!(window.slots.len(), 4);
+        assert_eq!(window.min_temp_c(), 4.0);
+        assert_eq!(window.max_temp_c(), 14.0);
+        assert_eq!(window.max_wind_kph(), 20.0);
+        assert!(window.is_wet());
+
+        let obs = window.to_observation();
+        assert_eq!(obs.local_time, "2026-10-19 05:30");
+        assert_eq!(obs.temp_c, 4.0);
+        assert_eq!(obs.wind_kph, 20.0);
+        assert_eq!(obs.condition, Condition::LightRain);
+        let warmest = window.warmest_observation();
+        assert_eq!(warmest.temp_c, 14.0);
+        assert_eq!(warmest.condition, Condition::LightRain);
+    }
+
+    #[test]
+    fn short_runs_stay_in_their_slot() {
+        let window = RunWindow::new(&dawn(), "2026-10-19 05:00", 45).unwrap();
+        assert_eq!(window.slots.len(), 1);
+        assert!(!window.is_wet());
+        assert!(RunWindow::new(&dawn(), "2026-10-20 05:00", 45).is_err());
+    }
+
+    #[test]
+    fn long_drizzles_are_not_downpours() {
+        let drizzle: Vec<Observation> = dawn()
+            .into_iter()
+            .map(|o| Observation { precip_mm: Some(0.8), ..o })
+            .collect();
+        let window = RunWindow::new(&drizzle, "2026-10-19 05:00", 300).unwrap();
+        assert_eq!(window.max_precip_mm(), Some(0.8));
+        assert_eq!(window.to_observation().precip_mm, Some(0.8));
+    }
+}
@@ -1,32 +1,101 @@
 
 extern crate hyper;
 extern crate rustc_serialize;
+extern crate time;
 
+use std;
 use std::env;
-use std::num;
 use std::fmt;
 use std::io::Read;
-use std::cmp::{Ord, Ordering};
+use std::num;
+use std::path::PathBuf;
 use self::rustc_serialize::base64::ToBase64;
-use std::iter::FromIterator;
 use self::rustc_serialize::base64;
+use select::document::Document;
 use select::node::Node;
-use select::predicate::Name;
-use std::collections::HashSet;
+use select::predicate::{Attr, Name, Predicate};
 
-use apixu_weather::{CurrentWeather, parse_hours_from_last_updated};
+use weather::{Condition, Observation, apparent_temperature_c, parse_hours_from_local_time};
+use cache;
 use cli::{Gender, Intensity};
+use config;
+use rain_gear::RainGear;
+use recommendation::{BodyPart, Garment, Picture, Recommendation, TermDimension};
 
 pub static RUNNERS_WORLD_URL: &'static str = "http://www.runnersworld.com/what-to-wear";
 
+/// How long a cached page is used for, unless the config file says otherwise.
+pub static DEFAULT_CACHE_HOURS: i64 = 24;
+
+/// What the scraper looks for on the page, from the outside in.
+static CONTENT_SELECTOR: &'static str = "#content";
+static CELLS_SELECTOR: &'static str = "#content table table td";
+static GARMENTS_SELECTOR: &'static str = "#content table table td p";
+
+#[derive(Debug)]
+pub enum ScrapeError {
+    FailedToContactRemoteHost(hyper::error::Error),
+    /// Runner's World answered with something else than 200 OK.
+    UnexpectedStatus(String),
+    /// Nothing on the page matches the selector: the markup has changed.
+    MissingElement(&'static str),
+}
+
+impl From<hyper::error::Error> for ScrapeError {
+    fn from(err: hyper::error::Error) -> ScrapeError {
+        ScrapeError::FailedToContactRemoteHost(err)
+    }
+}
+
+impl fmt::Display for ScrapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ScrapeError::FailedToContactRemoteHost(ref e) => {
+                write!(f, "Couldn't contact Runner's World website: {}", e)
+            }
+            ScrapeError::UnexpectedStatus(ref s) => write!(f, "Runner's World answered {}", s),
+            ScrapeError::MissingElement(selector) => {
+                write!(f,
+                       "Nothing matches `{}` on the Runner's World page, its markup must have \
+                        changed",
+                       selector)
+            }
+        }
+    }
+}
+
+/// Why an answer of the form was chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// The form field, e.g. "wind"
+    pub field: &'static str,
+    /// What the answer was chosen out of, e.g. "12.4 mph"
+    pub input: String,
+    /// The answer sent to the form, e.g. "lw"
+    pub bucket: &'static str,
+    /// The rule which picked the answer
+    pub rule: String,
+}
+
+/// The answers to the Runner's World "what to wear" form, which the local
+/// engine understands as well.
 pub struct FormBuilder {
-    gender: &'static str,
-    temperature: &'static str,
-    conditions: &'static str,
-    wind: &'static str,
-    time_of_day: &'static str,
-    intensity: &'static str,
-    feel: &'static str,
+    /// "m" or "f"
+    pub gender: &'static str,
+    /// The temperature bucket in fahrenheit, e.g. "45" or "zero"
+    pub temperature: &'static str,
+    /// "c", "pc", "o", "lr", "r" or "s"
+    pub conditions: &'static str,
+    /// "nw", "lw" or "hw"
+    pub wind: &'static str,
+    /// "dawn", "day", "dusk" or "night"
+    pub time_of_day: &'static str,
+    /// "n", "lr", "h" or "r"
+    pub intensity: &'static str,
+    /// "ib", "w" or "c"
+    pub feel: &'static str,
+    /// Why each answer (but the gender) was chosen
+    pub explanations: Vec<Explanation>,
 }
 
 impl FormBuilder {
@@ -42,199 +111,258 @@ impl FormBuilder {
                        self.feel);
     }
 
-    pub fn new(gender: &Gender, intensity: &Intensity, weather: &CurrentWeather) -> FormBuilder {
+    /// The answers for the given weather, as felt by a runner who feels
+    /// `thermal_offset_c` degrees warmer (or colder, if negative) than most.
+    pub fn new(gender: &Gender,
+               intensity: &Intensity,
+               weather: &Observation,
+               thermal_offset_c: f32)
+               -> FormBuilder {
+        let temp_f = weather.temp_f() + thermal_offset_c * 9.0 / 5.0;
+        let (temperature, temperature_rule) = FormBuilder::to_temperature(temp_f);
+        let (conditions, conditions_rule) = FormBuilder::to_conditions(weather);
+        let (wind, wind_rule) = FormBuilder::to_wind(weather);
+        let (time_of_day, time_of_day_rule) = FormBuilder::to_time_of_day(weather);
+        let (intensity_answer, intensity_rule) = FormBuilder::to_intensity(intensity);
+        let (feel, feel_rule) = FormBuilder::to_feel(weather, intensity);
+
+        let explanation = |field, input: String, bucket, rule: &str| {
+            Explanation {
+                field: field,
+                input: input,
+                bucket: bucket,
+                rule: rule.to_owned(),
+            }
+        };
+        let temp_input = if thermal_offset_c == 0.0 {
+            format!("{:.1}F", temp_f)
+        } else {
+            format!("{:.1}F ({:.1}F and a thermal offset of {:+.1}C)",
+                    temp_f,
+                    weather.temp_f(),
+                    thermal_offset_c)
+        };
+        let mut rain_input = weather.condition.name().to_owned();
+        if let Some(mm) = weather.precip_mm {
+            rain_input.push_str(&format!(", {:.1}mm", mm));
+        }
+        if let Some(c) = weather.precip_chance {
+            rain_input.push_str(&format!(", {}% chance", c));
+        }
+        let (apparent, apparent_wind) = FormBuilder::apparent_temperature(weather, intensity);
+        let explanations =
+            vec![explanation("temperature", temp_input, temperature, temperature_rule),
+                 explanation("conditions", rain_input, conditions, &conditions_rule),
+                 explanation("wind", format!("{:.1} mph", weather.wind_mph()), wind, wind_rule),
+                 explanation("time_of_day",
+                             format!("{} ({})",
+                                     weather.local_time,
+                                     if weather.is_day { "day" } else { "night" }),
+                             time_of_day,
+                             time_of_day_rule),
+                 explanation("intensity",
+                             format!("{}", intensity),
+                             intensity_answer,
+                             intensity_rule),
+                 explanation("feel",
+                             format!("{:.1}C, feeling {:.1}C with {:.0} km/h of wind and \
+                                      running pace",
+                                     weather.temp_c,
+                                     apparent,
+                                     apparent_wind),
+                             feel,
+                             feel_rule)];
+
         FormBuilder {
             gender: if *gender == Gender::Male { "m" } else { "f" },
-            temperature: FormBuilder::to_temperature(weather),
-            conditions: FormBuilder::to_conditions(weather),
-            wind: FormBuilder::to_wind(weather),
-            time_of_day: FormBuilder::to_time_of_day(weather),
-            intensity: FormBuilder::to_intensity(intensity),
-            feel: FormBuilder::to_feel(weather),
+            temperature: temperature,
+            conditions: conditions,
+            wind: wind,
+            time_of_day: time_of_day,
+            intensity: intensity_answer,
+            feel: feel,
+            explanations: explanations,
         }
     }
 
-    fn to_time_of_day(w: &CurrentWeather) -> &'static str {
-        match parse_hours_from_last_updated(&w.current.last_updated.to_string()) {
-            None => return "day", //Assume day by default
+    fn to_time_of_day(w: &Observation) -> (&'static str, &'static str) {
+        match parse_hours_from_local_time(&w.local_time) {
+            None => return ("day", "unknown local time, taken as day"), //Assume day by default
             Some(h) => {
-                if w.current.is_day == 0 {
-                    return "night";
+                if !w.is_day {
+                    return ("night", "the sun is down");
                 }
                 if h < 8 {
-                    return "dawn";
+                    return ("dawn", "before 8:00");
                 }
-                if h > 8 && h < 16 {
-                    return "day";
+                if h < 16 {
+                    return ("day", "from 8:00 to 16:00");
                 }
-                if h > 16 && h < 20 {
-                    return "dusk";
+                if h < 20 {
+                    return ("dusk", "from 16:00 to 20:00");
                 }
-                return "night";
+                return ("night", "after 20:00");
             }
         }
     }
 
     // Loosely based on: https://www.windfinder.com/wind/windspeed.htm
-    fn to_wind(w: &CurrentWeather) -> &'static str {
-        if w.current.wind_mph <= 3.0 {
-            return "nw";
+    fn to_wind(w: &Observation) -> (&'static str, &'static str) {
+        let wind_mph = w.wind_mph();
+        if wind_mph <= 3.0 {
+            return ("nw", "3 mph or less");
         }
-        if w.current.wind_mph > 3.0 && w.current.wind_mph < 25.0 {
-            return "lw";
+        if wind_mph > 3.0 && wind_mph < 25.0 {
+            return ("lw", "over 3 mph and under 25 mph");
         }
-        if w.current.wind_mph > 25.0 {
-            return "hw";
+        if wind_mph > 25.0 {
+            return ("hw", "over 25 mph");
         }
         // Assume no wind by default
-        return "nw";
+        return ("nw", "exactly 25 mph, taken as no wind");
     }
 
-    // TODO: Make more sophisticate
-    fn to_feel(w: &CurrentWeather) -> &'static str {
-        let real_temp = w.current.temp_c;
-        let feel_temp = w.current.feelslike_c;
-
-        if real_temp == feel_temp {
-            return "ib";
+    /// A typical pace for each intensity, in kph: running makes a wind of
+    /// its own, on top of the weather's.
+    fn running_speed_kph(i: &Intensity) -> f32 {
+        match *i {
+            Intensity::EasyRun | Intensity::LongRun => 10.0,
+            Intensity::HardWorkout => 13.0,
+            Intensity::Race => 15.0,
         }
-        if real_temp < (feel_temp + 10.0) {
-            return "w";
+    }
+
+    /// How warm it feels, and the wind it's felt in, in kph.
+    fn apparent_temperature(w: &Observation, intensity: &Intensity) -> (f32, f32) {
+        let apparent_wind = w.wind_kph + FormBuilder::running_speed_kph(intensity);
+        (apparent_temperature_c(w.temp_c, apparent_wind, w.relative_humidity()), apparent_wind)
+    }
+
+    /// Whether it feels colder or warmer than it is, out of the wind chill
+    /// (counting the runner's own speed) or the heat index.
+    fn to_feel(w: &Observation, intensity: &Intensity) -> (&'static str, &'static str) {
+        let (apparent, _) = FormBuilder::apparent_temperature(w, intensity);
+        if apparent <= w.temp_c - 3.0 {
+            return ("c", "feeling at least 3C colder than the air");
         }
-        if real_temp > (feel_temp + 10.0) {
-            return "c";
+        if apparent >= w.temp_c + 3.0 {
+            return ("w", "feeling at least 3C warmer than the air");
         }
-
-        // Assume in-between otherwise
-        return "ib";
+        return ("ib", "feeling within 3C of the air");
     }
 
-    fn to_conditions(w: &CurrentWeather) -> &'static str {
-        let weather_code = w.current.condition.code;
-        if weather_code == 1000 {
-            return "c";
-        };
-        if weather_code >= 1003 {
-            return "pc";
-        };
-        if weather_code > 1003 && weather_code <= 1009 {
-            return "o";
-        };
-        if weather_code == 1183 {
-            return "lr";
-        };
-        if weather_code != 1183 && weather_code > 1009 && weather_code < 1195 {
-            return "r";
-        };
-        // Assume snow otherwise
-        return "s";
+    /// How much it rains comes from the precipitation amount and probability,
+    /// when the provider tells them, rather than the condition alone.
+    fn to_conditions(w: &Observation) -> (&'static str, String) {
+        match (w.condition, RainGear::of(w)) {
+            (Condition::Sleet, _) | (Condition::Snow, _) => {
+                ("s", "it's snowing or sleeting".to_owned())
+            }
+            (Condition::Clear, RainGear::NoRainGear) => {
+                ("c", "clear sky, and no rain gear needed".to_owned())
+            }
+            (Condition::PartlyCloudy, RainGear::NoRainGear) => {
+                ("pc", "partly cloudy, and no rain gear needed".to_owned())
+            }
+            (_, RainGear::NoRainGear) => ("o", "no rain gear needed".to_owned()),
+            (_, gear @ RainGear::Shell) => ("lr", format!("it rains enough for {}", gear)),
+            (_, gear @ RainGear::Waterproof) |
+            (_, gear @ RainGear::ChangeOfSocks) => ("r", format!("it rains enough for {}", gear)),
+        }
     }
 
-    fn to_intensity(i: &Intensity) -> &'static str {
+    fn to_intensity(i: &Intensity) -> (&'static str, &'static str) {
+        let rule = "as given with --intensity";
         match *i {
-            Intensity::EasyRun => return "n",
-            Intensity::LongRun => return "lr",
-            Intensity::HardWorkout => return "h",
-            Intensity::Race => return "r",
+            Intensity::EasyRun => return ("n", rule),
+            Intensity::LongRun => return ("lr", rule),
+            Intensity::HardWorkout => return ("h", rule),
+            Intensity::Race => return ("r", rule),
         }
     }
 
-    fn to_temperature(w: &CurrentWeather) -> &'static str {
-        let temp = w.current.temp_f;
+    fn to_temperature(temp: f32) -> (&'static str, &'static str) {
         if temp < -5.0 {
-            return "-10";
+            return ("-10", "below -5F");
         }
         if temp >= -5.0 && temp < 0.0 {
-            return "-5";
+            return ("-5", "from -5F to 0F");
         }
         if temp >= 0.0 && temp < 5.0 {
-            return "zero";
+            return ("zero", "from 0F to 5F");
         }
         if temp >= 5.0 && temp < 10.0 {
-            return "5";
+            return ("5", "from 5F to 10F");
         }
         if temp >= 10.0 && temp < 15.0 {
-            return "10";
+            return ("10", "from 10F to 15F");
         }
         if temp >= 15.0 && temp < 20.0 {
-            return "15";
+            return ("15", "from 15F to 20F");
         }
         if temp >= 20.0 && temp < 25.0 {
-            return "20";
+            return ("20", "from 20F to 25F");
         }
         if temp >= 25.0 && temp < 30.0 {
-            return "25";
+            return ("25", "from 25F to 30F");
         }
         if temp >= 30.0 && temp < 35.0 {
-            return "30";
+            return ("30", "from 30F to 35F");
         }
         if temp >= 35.0 && temp < 40.0 {
-            return "35";
+            return ("35", "from 35F to 40F");
         }
         if temp >= 40.0 && temp < 45.0 {
-            return "40";
+            return ("40", "from 40F to 45F");
         }
         if temp >= 45.0 && temp < 50.0 {
-            return "45";
+            return ("45", "from 45F to 50F");
         }
         if temp >= 50.0 && temp < 55.0 {
-            return "50";
+            return ("50", "from 50F to 55F");
         }
         if temp >= 55.0 && temp < 60.0 {
-            return "55";
+            return ("55", "from 55F to 60F");
         }
         if temp >= 60.0 && temp < 65.0 {
-            return "60";
+            return ("60", "from 60F to 65F");
         }
         if temp >= 65.0 && temp < 70.0 {
-            return "65";
+            return ("65", "from 65F to 70F");
         }
         if temp >= 70.0 && temp < 75.0 {
-            return "70";
+            return ("70", "from 70F to 75F");
         }
         if temp >= 75.0 && temp < 80.0 {
-            return "75";
+            return ("75", "from 75F to 80F");
         }
         if temp >= 80.0 && temp < 85.0 {
-            return "80";
+            return ("80", "from 80F to 85F");
         }
         if temp >= 85.0 && temp < 90.0 {
-            return "85";
+            return ("85", "from 85F to 90F");
         }
         if temp >= 90.0 && temp < 95.0 {
-            return "90";
+            return ("90", "from 90F to 95F");
         }
         if temp >= 95.0 && temp < 100.0 {
-            return "95";
+            return ("95", "from 95F to 100F");
         }
-        if w.current.temp_f >= 100.0 {
-            return "100";
+        if temp >= 100.0 {
+            return ("100", "100F or more");
         }
-        return "100";
+        return ("100", "unknown, taken as 100F or more");
     }
 }
 
-#[derive(Eq, Debug, PartialOrd, PartialEq, Hash)]
-pub struct Image<'a> {
-    pub url: &'a str,
+/// A picture of a garment, as found on the page.
+#[derive(Debug, Clone, PartialEq)]
+struct Image<'a> {
+    url: &'a str,
     width: TermDimension,
     height: TermDimension,
 }
 
-#[derive(Debug, Eq, PartialOrd, PartialEq, Hash)]
-enum TermDimension {
-    Auto,
-    Dimension(u8),
-}
-
-#[derive(Debug, Ord, Eq, PartialOrd, PartialEq)]
-enum BodyPart {
-    Head,
-    Torso,
-    Legs,
-    Feet,
-}
-
 impl<'a> Image<'a> {
     fn body_part(&self) -> Option<BodyPart> {
         if self.url.contains("head") {
@@ -251,27 +379,47 @@ impl<'a> Image<'a> {
         }
         return None;
     }
-}
 
-// Order an image according to the part of the body: head, legs, torso, feet.
-impl<'a> Ord for Image<'a> {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (self.body_part(), other.body_part()) {
-            (None, None) => Ordering::Equal,
-            (Some(_), None) => Ordering::Greater,
-            (None, Some(_)) => Ordering::Less,
-            (Some(a), Some(b)) => a.cmp(&b),
+    /// Whether the picture is named after the garment, e.g.
+    /// `.../torso/long_sleeve_shirt.png` and "Long-Sleeve Shirt".
+    fn shows(&self, garment: &str) -> bool {
+        let file = self.url.rsplit('/').next().unwrap_or(self.url);
+        let file_words = words(file.split('.').next().unwrap_or(file));
+        let garment_words = words(garment);
+        !garment_words.is_empty() && garment_words.iter().all(|w| file_words.contains(w))
+    }
+
+    fn to_picture(&self) -> Picture {
+        Picture {
+            url: self.url.to_owned(),
+            width: self.width,
+            height: self.height,
         }
     }
 }
 
-impl fmt::Display for TermDimension {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            TermDimension::Auto => write!(f, "auto"),
-            TermDimension::Dimension(d) => write!(f, "{}", d),
+fn words(input: &str) -> Vec<String> {
+    input.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect()
+}
+
+/// The body part a garment is worn on, guessed out of its name when there is
+/// no picture of it. Legs come first, or "capris" would be a cap.
+fn guess_body_part(garment: &str) -> BodyPart {
+    let keywords = [(BodyPart::Legs, vec!["tights", "shorts", "pants", "capri", "brief", "skirt"]),
+                    (BodyPart::Hands, vec!["glove", "mitten"]),
+                    (BodyPart::Feet, vec!["sock", "shoe"]),
+                    (BodyPart::Head,
+                     vec!["hat", "cap", "headband", "visor", "balaclava", "sunglasses", "beanie"])];
+    let garment_words = words(garment);
+    for &(body_part, ref names) in keywords.iter() {
+        if garment_words.iter().any(|w| names.iter().any(|n| w.starts_with(n))) {
+            return body_part;
         }
     }
+    BodyPart::Torso
 }
 
 pub fn download_img(client: &hyper::client::Client,
@@ -316,7 +464,7 @@ fn user_terminal() -> TerminalInUse {
 // a workaround. One could be to use https://github.com/PistonDevelopers/image
 // to load the image in memory and get its dimension, and using explicit height
 // in the iTerm inline capability.
-pub fn display_inline_image(img_info: &Image, img_mb: Result<String, hyper::error::Error>) {
+pub fn display_inline_image(img_info: &Picture, img_mb: Result<String, hyper::error::Error>) {
     match img_mb {
         Err(_) => return,
         Ok(i) => {
@@ -334,13 +482,166 @@ pub fn display_inline_image(img_info: &Image, img_mb: Result<String, hyper::erro
     }
 }
 
-pub fn find_images<'a>(tds: &'a Vec<Node>) -> HashSet<Image<'a>> {
-    let mut images0 = Vec::new();
-    for td in tds {
-        images0.extend(td.find(Name("img")).filter_map(|x| mk_image(&x)));
+#[derive(Serialize, Deserialize, Debug)]
+struct CachedPage {
+    /// When the page was fetched, in seconds since the epoch
+    fetched_at: i64,
+    body: String,
+}
+
+/// The pages already fetched, on disk. The answers of the form are bucketed,
+/// so the same few pages are asked for over and over.
+pub struct PageCache {
+    dir: Option<PathBuf>,
+    ttl_secs: i64,
+}
+
+impl PageCache {
+    /// A cache under `$XDG_CACHE_HOME/viktor`, keeping the pages for
+    /// `ttl_hours` (not at all, if 0).
+    pub fn new(ttl_hours: i64) -> PageCache {
+        PageCache::with_dir(config::cache_dir().map(|d| d.join("runners_world")),
+                            ttl_hours * 3600)
     }
 
-    HashSet::from_iter(images0)
+    pub fn with_dir(dir: Option<PathBuf>, ttl_secs: i64) -> PageCache {
+        PageCache {
+            dir: dir,
+            ttl_secs: ttl_secs,
+        }
+    }
+
+    /// The file of the page at the given url, named after its query string.
+    /// Any byte but ASCII letters, digits and '-' is escaped as `_` and its
+    /// hex code, so that different queries never share a file.
+    fn file(&self, url: &str) -> Option<PathBuf> {
+        let query = url.splitn(2, '?').nth(1).unwrap_or(url);
+        let mut name = String::new();
+        for b in query.bytes() {
+            match b {
+                b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'-' => name.push(b as char),
+                _ => name.push_str(&format!("_{:02X}", b)),
+            }
+        }
+        self.dir.as_ref().map(|d| d.join(format!("{}.json", name)))
+    }
+
+    /// The page at the given url, unless it's not cached or too old.
+    pub fn get(&self, url: &str) -> Option<String> {
+        let cached: CachedPage = match self.file(url).and_then(|f| cache::read_json(&f)) {
+            Some(c) => c,
+            None => return None,
+        };
+        if time::get_time().sec - cached.fetched_at >= self.ttl_secs {
+            return None;
+        }
+        Some(cached.body)
+    }
+
+    /// Remembers the page at the given url, as fetched now.
+    pub fn put(&self, url: &str, body: &str) -> Result<(), std::io::Error> {
+        if self.ttl_secs <= 0 {
+            return Ok(());
+        }
+        match self.file(url) {
+            None => Ok(()),
+            Some(f) => {
+                cache::write_json(&f,
+                                  &CachedPage {
+                                      fetched_at: time::get_time().sec,
+                                      body: body.to_owned(),
+                                  })
+            }
+        }
+    }
+}
+
+/// Fetches the "what to wear" page for the given form answers.
+pub fn fetch_page(client: &hyper::client::Client, url: &str) -> Result<String, ScrapeError> {
+    let mut response = try!(client.get(url).send());
+    if response.status != hyper::status::StatusCode::Ok {
+        return Err(ScrapeError::UnexpectedStatus(response.status.to_string()));
+    }
+    let mut body = String::new();
+    let _ = try!(response.read_to_string(&mut body).map_err(hyper::error::Error::from));
+    Ok(body)
+}
+
+/// What Runner's World recommends on the given "what to wear" page, failing
+/// with the first selector which matches nothing.
+pub fn scrape(document: &Document) -> Result<Recommendation, ScrapeError> {
+    let content: Vec<_> = document.find(Attr("id", "content")).collect();
+    if content.is_empty() {
+        return Err(ScrapeError::MissingElement(CONTENT_SELECTOR));
+    }
+    let mut items = Vec::new();
+    let mut cells = 0;
+    for node in content {
+        let pred = Name("table").descendant(Name("table").descendant(Name("td")));
+        let tds: Vec<_> = node.find(pred).collect();
+        cells += tds.len();
+        items.extend(find_recommendation(&tds).items);
+    }
+    if cells == 0 {
+        return Err(ScrapeError::MissingElement(CELLS_SELECTOR));
+    }
+    if items.is_empty() {
+        return Err(ScrapeError::MissingElement(GARMENTS_SELECTOR));
+    }
+    Ok(Recommendation::new(items))
+}
+
+/// A garment with its picture, if any. Unless the page tells, the body part
+/// comes from the picture, or from the name.
+fn garment(name: String,
+           description: String,
+           body_part: Option<BodyPart>,
+           image: Option<&Image>)
+           -> Garment {
+    let body_part = body_part.or(image.and_then(|i| i.body_part()))
+        .unwrap_or(guess_body_part(&name));
+    Garment {
+        body_part: body_part,
+        name: name,
+        description: description,
+        image: image.map(|i| i.to_picture()),
+    }
+}
+
+/// The garments described in the form cells, each with its picture if the
+/// page has one.
+fn find_recommendation(tds: &Vec<Node>) -> Recommendation {
+    let images = find_images(tds);
+    let items = find_descriptions(tds)
+        .into_iter()
+        .map(|(name, description)| {
+            match images.iter().find(|i| i.shows(&name)) {
+                Some(image) => garment(name, description, None, Some(image)),
+                None => {
+                    // The only picture of the body part must be it.
+                    let body_part = guess_body_part(&name);
+                    let of_body_part: Vec<_> =
+                        images.iter().filter(|i| i.body_part() == Some(body_part)).collect();
+                    let only = if of_body_part.len() == 1 { Some(of_body_part[0]) } else { None };
+                    garment(name, description, Some(body_part), only)
+                }
+            }
+        })
+        .collect();
+    Recommendation::new(items)
+}
+
+/// The pictures of the garments, in page order, once each.
+fn find_images<'a>(tds: &'a Vec<Node>) -> Vec<Image<'a>> {
+    let mut images: Vec<Image<'a>> = Vec::new();
+    for td in tds {
+        for image in td.find(Name("img")).filter_map(|x| mk_image(&x)) {
+            if !images.contains(&image) {
+                images.push(image);
+            }
+        }
+    }
+    images
 }
 
 fn mk_image<'a>(img_node: &Node<'a>) -> Option<Image<'a>> {
@@ -368,18 +669,23 @@ fn mk_image<'a>(img_node: &Node<'a>) -> Option<Image<'a>> {
     }
 }
 
-pub fn find_descriptions(tds: &Vec<Node>) -> HashSet<(String, String)> {
+/// The garments and their descriptions, in page order, once each.
+fn find_descriptions(tds: &Vec<Node>) -> Vec<(String, String)> {
     let mut descs = Vec::new();
     for td in tds.iter().skip(1) {
-        descs.extend(td.find(Name("p")).filter_map(|x| filter_description(x)));
+        for desc in td.find(Name("p")).filter_map(|x| filter_description(x)) {
+            if !descs.contains(&desc) {
+                descs.push(desc);
+            }
+        }
     }
-
-    HashSet::from_iter(descs)
+    descs
 }
 
 fn filter_description(d: Node) -> Option<(String, String)> {
     match (d.first_child().map(|v| v.text()), d.last_child().map(|v| v.text())) {
         (Some(item), Some(desc)) => {
+            let (item, desc) = (item.trim().to_owned(), desc.trim().to_owned());
             if desc.is_empty() || desc.contains("Revise Conditions") {
                 None
             } else {
@@ -393,7 +699,170 @@ fn filter_description(d: Node) -> Option<(String, String)> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::env;
+    use std::fs;
     use hyper::client::Client;
+    use select::document::Document;
+    use cli::{Gender, Intensity};
+    use recommendation::{BodyPart, TermDimension};
+    use weather::{Condition, Observation};
+
+    #[test]
+    fn every_condition_gets_its_own_answer() {
+        let conditions = |condition| {
+            FormBuilder::to_conditions(&Observation { condition: condition, ..Default::default() }).0
+        };
+        assert_eq!(conditions(Condition::Clear), "c");
+        assert_eq!(conditions(Condition::PartlyCloudy), "pc");
+        assert_eq!(conditions(Condition::Fog), "o");
+        assert_eq!(conditions(Condition::Drizzle), "lr");
+        assert_eq!(conditions(Condition::Thunderstorm), "r");
+        assert_eq!(conditions(Condition::Snow), "s");
+    }
+
+    #[test]
+    fn the_hours_on_the_boundaries_have_a_time_of_day() {
+        let at = |local_time: &str| {
+            FormBuilder::to_time_of_day(&Observation {
+                    local_time: local_time.to_owned(),
+                    is_day: true,
+                    ..Default::default()
+                })
+                .0
+        };
+        assert_eq!(at("2026-10-18 07:59"), "dawn");
+        assert_eq!(at("2026-10-18 08:00"), "day");
+        assert_eq!(at("2026-10-18 16:00"), "dusk");
+        assert_eq!(at("2026-10-18 20:00"), "night");
+    }
+
+    #[test]
+    fn the_thermal_offset_shifts_the_temperature_bucket() {
+        let weather = Observation { temp_c: 10.0, feelslike_c: 10.0, ..Default::default() };
+        let form = |offset| FormBuilder::new(&Gender::Female, &Intensity::EasyRun, &weather, offset);
+        assert_eq!(form(0.0).temperature, "50");
+        assert_eq!(form(3.0).temperature, "55");
+        assert_eq!(form(-3.0).temperature, "40");
+    }
+
+    #[test]
+    fn tells_drizzle_from_a_downpour() {
+        let rain = |precip_mm| {
+            Observation {
+                condition: Condition::Rain,
+                precip_mm: Some(precip_mm),
+                ..Default::default()
+            }
+        };
+        assert_eq!(FormBuilder::to_conditions(&rain(0.3)).0, "lr");
+        assert_eq!(FormBuilder::to_conditions(&rain(5.0)).0, "r");
+        let unlikely = Observation { precip_chance: Some(10), ..rain(5.0) };
+        assert_eq!(FormBuilder::to_conditions(&unlikely).0, "o");
+    }
+
+    #[test]
+    fn feels_the_wind_chill_and_the_heat_index() {
+        let weather = |temp_c, wind_kph, humidity| {
+            Observation {
+                temp_c: temp_c,
+                feelslike_c: temp_c,
+                wind_kph: wind_kph,
+                humidity: Some(humidity),
+                ..Default::default()
+            }
+        };
+        let feel = |w: &Observation, i: &Intensity| FormBuilder::new(&Gender::Male, i, w, 0.0).feel;
+        assert_eq!(feel(&weather(0.0, 20.0, 60), &Intensity::EasyRun), "c");
+        assert_eq!(feel(&weather(18.0, 20.0, 60), &Intensity::EasyRun), "ib");
+        assert_eq!(feel(&weather(32.0, 5.0, 70), &Intensity::EasyRun), "w");
+        assert_eq!(feel(&weather(30.0, 5.0, 20), &Intensity::Race), "ib");
+        // Still air, but the runner's own speed chills them
+        assert_eq!(feel(&weather(2.0, 0.0, 60), &Intensity::Race), "c");
+    }
+
+    #[test]
+    fn explains_every_answer_of_the_form() {
+        let weather = Observation {
+            local_time: "2026-10-18 06:30".to_owned(),
+            is_day: true,
+            temp_c: 10.0,
+            feelslike_c: 10.0,
+            wind_kph: 20.0,
+            condition: Condition::Rain,
+            precip_mm: Some(0.3),
+            ..Default::default()
+        };
+        let form = FormBuilder::new(&Gender::Male, &Intensity::LongRun, &weather, 3.0);
+        let explanations = &form.explanations;
+        let fields: Vec<&str> = explanations.iter().map(|e| e.field).collect();
+        assert_eq!(fields,
+                   vec!["temperature", "conditions", "wind", "time_of_day", "intensity", "feel"]);
+        let buckets: Vec<&str> = explanations.iter().map(|e| e.bucket).collect();
+        assert_eq!(buckets, vec!["55", "lr", "lw", "dawn", "lr", "c"]);
+        assert_eq!(explanations[0].input, "55.4F (50.0F and a thermal offset of +3.0C)");
+        assert_eq!(explanations[0].rule, "from 55F to 60F");
+        assert_eq!(explanations[1].rule, "it rains enough for a water-resistant shell");
+        assert_eq!(explanations[3].rule, "before 8:00");
+    }
+
+    #[test]
+    fn scrapes_the_garments_with_their_pictures() {
+        let page = include_str!("../fixtures/runners_world/what_to_wear.html");
+        let items = scrape(&Document::from(page)).unwrap().items;
+        let names: Vec<&str> = items.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["Hat", "Long-Sleeve Shirt", "Gloves", "Tights", "Running Socks"]);
+        assert_eq!(items[1].body_part, BodyPart::Torso);
+        assert_eq!(items[1].description, "A technical long-sleeve shirt, wicking the sweat away.");
+        let shirt = items[1].image.as_ref().unwrap();
+        assert!(shirt.url.ends_with("/torso/long_sleeve_shirt.png"));
+        assert_eq!((shirt.width, shirt.height),
+                   (TermDimension::Dimension(150), TermDimension::Dimension(140)));
+        assert_eq!(items[2].body_part, BodyPart::Hands);
+        assert_eq!(items[2].image, None);
+        assert!(items[4].image.as_ref().unwrap().url.ends_with("/feet/running_socks.png"));
+    }
+
+    #[test]
+    fn tells_which_part_of_the_page_is_missing() {
+        let missing = |page: &str| match scrape(&Document::from(page)) {
+            Err(ScrapeError::MissingElement(selector)) => selector,
+            other => panic!("Expected a MissingElement error, got {:?}", other),
+        };
+        assert_eq!(missing("<html><body><p>Page not found</p></body></html>"), "#content");
+        assert_eq!(missing("<div id=\"content\"><p>We moved!</p></div>"),
+                   "#content table table td");
+        assert_eq!(missing("<div id=\"content\"><table><tr><td><table><tr><td>Hello</td>\
+                            <td>No garments</td></tr></table></td></tr></table></div>"),
+                   "#content table table td p");
+    }
+
+    #[test]
+    fn caches_the_pages_until_they_expire() {
+        let dir = env::temp_dir().join("viktor-test-runners-world");
+        let _ = fs::remove_dir_all(&dir);
+        let url = "http://www.runnersworld.com/what-to-wear?gender=m&temp=-5&conditions=s";
+        let fresh = PageCache::with_dir(Some(dir.clone()), 3600);
+        assert_eq!(fresh.get(url), None);
+        fresh.put(url, "<html></html>").unwrap();
+        assert_eq!(fresh.get(url), Some("<html></html>".to_owned()));
+        assert!(dir.join("gender_3Dm_26temp_3D-5_26conditions_3Ds.json").exists());
+        assert_eq!(fresh.get("http://www.runnersworld.com/what-to-wear?gender=f"), None);
+        assert_eq!(fresh.get("http://www.runnersworld.com/what-to-wear?gender_m&temp=-5&conditions=s"),
+                   None);
+
+        let expired = PageCache::with_dir(Some(dir.clone()), 0);
+        assert_eq!(expired.get(url), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn guesses_the_body_part_of_a_garment() {
+        assert_eq!(guess_body_part("Capris"), BodyPart::Legs);
+        assert_eq!(guess_body_part("Cap"), BodyPart::Head);
+        assert_eq!(guess_body_part("Light Gloves"), BodyPart::Hands);
+        assert_eq!(guess_body_part("Wind Vest"), BodyPart::Torso);
+    }
+
     #[test]
     #[ignore]
     fn can_download_img() {
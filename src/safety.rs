@@ -0,0 +1,110 @@
+//! What to wear to be seen, when it's dark or the visibility is poor.
+
+use std::fmt;
+
+use recommendation::{BodyPart, Garment, Recommendation};
+use weather::{Condition, Observation};
+
+/// Below this, in kilometers, drivers won't see you coming (mist, heavy
+/// rain or snow, fog).
+static LOW_VISIBILITY_KM: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hazard {
+    /// Night, or the twilight around dawn and dusk
+    Dark,
+    /// Fog, mist or anything else hiding you from traffic
+    LowVisibility,
+}
+
+impl fmt::Display for Hazard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Hazard::Dark => write!(f, "it's dark"),
+            Hazard::LowVisibility => write!(f, "the visibility is poor"),
+        }
+    }
+}
+
+/// What makes it hard to be seen, given the weather and the time of day of
+/// the Runner's World form ("dawn", "day", "dusk" or "night").
+pub fn hazards(weather: &Observation, time_of_day: &str) -> Vec<Hazard> {
+    let mut hazards = Vec::new();
+    if !weather.is_day || time_of_day != "day" {
+        hazards.push(Hazard::Dark);
+    }
+    let poor_visibility = weather.visibility_km.map(|v| v < LOW_VISIBILITY_KM).unwrap_or(false);
+    if weather.condition == Condition::Fog || poor_visibility {
+        hazards.push(Hazard::LowVisibility);
+    }
+    hazards
+}
+
+/// The safety gear against the given hazards, if any.
+pub fn safety_gear(hazards: &[Hazard]) -> Recommendation {
+    if hazards.is_empty() {
+        return Recommendation::default();
+    }
+    let garment = |body_part, name: &str, description: &str| {
+        Garment {
+            body_part: body_part,
+            name: name.to_owned(),
+            description: description.to_owned(),
+            image: None,
+        }
+    };
+    let mut items = vec![];
+    if hazards.contains(&Hazard::Dark) {
+        items.push(garment(BodyPart::Head,
+                           "Headlamp",
+                           "To see the road, and to be seen from the front."));
+    }
+    items.push(garment(BodyPart::Torso,
+                       "Reflective vest",
+                       "Or any top with reflective panels: headlights pick them up from afar."));
+    if !hazards.contains(&Hazard::Dark) {
+        items.push(garment(BodyPart::Torso,
+                           "Bright colours",
+                           "Fluorescent yellow or orange stand out in the fog, black doesn't."));
+    }
+    items.push(garment(BodyPart::Hands,
+                       "Blinking lights",
+                       "A white one in front and a red one on your back, or on your arms."));
+    items.push(garment(BodyPart::Feet,
+                       "Reflective ankle bands",
+                       "Moving feet are the first thing drivers notice."));
+    Recommendation::new(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use weather::{Condition, Observation};
+
+    fn names(hazards: &[Hazard]) -> Vec<String> {
+        safety_gear(hazards).items.into_iter().map(|g| g.name).collect()
+    }
+
+    #[test]
+    fn winter_commutes_need_lights() {
+        let evening = Observation { is_day: false, ..Default::default() };
+        assert_eq!(hazards(&evening, "night"), vec![Hazard::Dark]);
+        let items = names(&hazards(&evening, "night"));
+        assert!(items.contains(&"Headlamp".to_owned()));
+        assert!(items.contains(&"Reflective vest".to_owned()));
+        assert!(items.contains(&"Blinking lights".to_owned()));
+    }
+
+    #[test]
+    fn foggy_days_need_to_be_seen_too() {
+        let fog = Observation { is_day: true, condition: Condition::Fog, ..Default::default() };
+        assert_eq!(hazards(&fog, "day"), vec![Hazard::LowVisibility]);
+        let mist = Observation { is_day: true, visibility_km: Some(1.2), ..Default::default() };
+        assert_eq!(hazards(&mist, "day"), vec![Hazard::LowVisibility]);
+        assert!(!names(&hazards(&fog, "day")).contains(&"Headlamp".to_owned()));
+
+        let clear = Observation { is_day: true, visibility_km: Some(10.0), ..Default::default() };
+        assert!(hazards(&clear, "day").is_empty());
+        assert!(safety_gear(&[]).items.is_empty());
+    }
+}
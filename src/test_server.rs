@@ -0,0 +1,71 @@
+//! A tiny HTTP server standing in for the remote APIs in tests, so that
+//! providers can be exercised against recorded fixtures.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+pub struct Reply {
+    pub status: &'static str,
+    pub headers: Vec<(&'static str, String)>,
+    pub body: String,
+}
+
+pub fn ok(body: &str) -> Reply {
+    Reply {
+        status: "200 OK",
+        headers: vec![("Content-Type", "application/json".to_owned())],
+        body: body.to_owned(),
+    }
+}
+
+/// Serves the given replies on a random local port, one per connection and
+/// in order. Returns the base url of the server, together with a channel
+/// yielding the head (request line and headers) of every request received.
+pub fn serve(replies: Vec<Reply>) -> (String, Receiver<String>) {
+    serve_with(|_| replies)
+}
+
+/// Like `serve`, but the replies are built once the base url of the server
+/// is known, for fixtures which link back to the server itself.
+pub fn serve_with<F>(mk_replies: F) -> (String, Receiver<String>)
+    where F: FnOnce(&str) -> Vec<Reply>
+{
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Couldn't bind the test server.");
+    let addr = listener.local_addr().expect("Couldn't get the test server address.");
+    let base_url = format!("http://{}", addr);
+    let replies = mk_replies(&base_url);
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        for reply in replies {
+            let mut stream = match listener.accept() {
+                Ok((s, _)) => s,
+                Err(_) => return,
+            };
+            let mut head = String::new();
+            {
+                let mut reader = BufReader::new(&stream);
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+            }
+            let _ = tx.send(head);
+
+            let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+                                       reply.status,
+                                       reply.body.len());
+            for &(name, ref value) in reply.headers.iter() {
+                response.push_str(&format!("{}: {}\r\n", name, value));
+            }
+            response.push_str("\r\n");
+            response.push_str(&reply.body);
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+    (base_url, rx)
+}
@@ -0,0 +1,84 @@
+//! How much sun protection a run needs, out of the UV index.
+
+use std::fmt;
+
+use weather::Observation;
+
+pub static UV_BANDS: [&'static str; 5] = ["low", "moderate", "high", "very-high", "extreme"];
+
+/// The exposure categories of the WHO Global Solar UV Index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum UvBand {
+    Low,
+    Moderate,
+    High,
+    VeryHigh,
+    Extreme,
+}
+
+impl UvBand {
+    pub fn from_index(uv_index: f32) -> UvBand {
+        // The index is reported rounded to the nearest integer.
+        let index = uv_index.round();
+        if index < 3.0 {
+            return UvBand::Low;
+        }
+        if index < 6.0 {
+            return UvBand::Moderate;
+        }
+        if index < 8.0 {
+            return UvBand::High;
+        }
+        if index < 11.0 {
+            return UvBand::VeryHigh;
+        }
+        UvBand::Extreme
+    }
+
+    /// The UV band of the given weather, if the provider reports the index.
+    pub fn of(weather: &Observation) -> Option<UvBand> {
+        weather.uv_index.map(UvBand::from_index)
+    }
+
+    /// The name used in the clothing rules, e.g. "very-high".
+    pub fn name(&self) -> &'static str {
+        UV_BANDS[*self as usize]
+    }
+
+    pub fn advice(&self) -> &'static str {
+        match *self {
+            UvBand::Low => "No sun protection needed.",
+            UvBand::Moderate => "Wear sunscreen if you'll be out for long.",
+            UvBand::High => "Wear sunscreen, a cap and sunglasses.",
+            UvBand::VeryHigh => "Cover up: you can get burned in less than half an hour.",
+            UvBand::Extreme => "Avoid the midday sun: unprotected skin burns in minutes.",
+        }
+    }
+}
+
+impl fmt::Display for UvBand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            UvBand::Low => write!(f, "Low"),
+            UvBand::Moderate => write!(f, "Moderate"),
+            UvBand::High => write!(f, "High"),
+            UvBand::VeryHigh => write!(f, "Very high"),
+            UvBand::Extreme => write!(f, "Extreme"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uses_the_who_exposure_categories() {
+        assert_eq!(UvBand::from_index(0.4), UvBand::Low);
+        assert_eq!(UvBand::from_index(2.6), UvBand::Moderate);
+        assert_eq!(UvBand::from_index(7.0), UvBand::High);
+        assert_eq!(UvBand::from_index(9.0), UvBand::VeryHigh);
+        assert_eq!(UvBand::from_index(11.0), UvBand::Extreme);
+        assert_eq!(UvBand::VeryHigh.name(), "very-high");
+    }
+}
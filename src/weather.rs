@@ -0,0 +1,593 @@
+extern crate hyper;
+extern crate serde_json;
+extern crate time;
+
+use std;
+
+pub use hyper::client::Client;
+use std::io::Read;
+
+use apixu_weather::Apixu;
+use config::Settings;
+use met_norway_weather::MetNorway;
+use nws_weather::Nws;
+use open_meteo_weather::OpenMeteo;
+use openweathermap_weather::{OpenWeatherMap, Units};
+
+static VIKTOR_HOMEPAGE: &'static str = "https://github.com/adinapoli/viktor";
+
+pub static DEFAULT_PROVIDER: &'static str = "apixu";
+pub static PROVIDERS: [&'static str; 5] = ["apixu", "open-meteo", "openweathermap", "met-norway",
+                                           "nws"];
+
+/// Where the user would like to know the weather for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    /// Let the provider infer the location (e.g. from the IP address).
+    AutoIp,
+    City(String),
+    /// Latitude and longitude in decimal degrees.
+    Coords(f32, f32),
+}
+
+impl Query {
+    pub fn new(city: &Option<String>, coords: &Option<(f32, f32)>) -> Query {
+        match (city, coords) {
+            (_, &Some((lat, lon))) => Query::Coords(lat, lon),
+            (&Some(ref c), &None) => Query::City(c.clone()),
+            (&None, &None) => Query::AutoIp,
+        }
+    }
+}
+
+/// Coarse weather categories every provider maps its own codes onto.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Overcast,
+    Fog,
+    Drizzle,
+    LightRain,
+    Rain,
+    HeavyRain,
+    Thunderstorm,
+    Sleet,
+    Snow,
+}
+
+/// The names conditions can be given with, e.g. in a weather file.
+pub static CONDITIONS: [&'static str; 11] = ["clear", "partly-cloudy", "overcast", "fog", "drizzle",
+                                              "light-rain", "rain", "heavy-rain", "thunderstorm",
+                                              "sleet", "snow"];
+
+impl Condition {
+    pub fn parse(input: &str) -> Option<Condition> {
+        match input {
+            "clear" => Some(Condition::Clear),
+            "partly-cloudy" => Some(Condition::PartlyCloudy),
+            "overcast" => Some(Condition::Overcast),
+            "fog" => Some(Condition::Fog),
+            "drizzle" => Some(Condition::Drizzle),
+            "light-rain" => Some(Condition::LightRain),
+            "rain" => Some(Condition::Rain),
+            "heavy-rain" => Some(Condition::HeavyRain),
+            "thunderstorm" => Some(Condition::Thunderstorm),
+            "sleet" => Some(Condition::Sleet),
+            "snow" => Some(Condition::Snow),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Condition::Clear => "clear",
+            Condition::PartlyCloudy => "partly-cloudy",
+            Condition::Overcast => "overcast",
+            Condition::Fog => "fog",
+            Condition::Drizzle => "drizzle",
+            Condition::LightRain => "light-rain",
+            Condition::Rain => "rain",
+            Condition::HeavyRain => "heavy-rain",
+            Condition::Thunderstorm => "thunderstorm",
+            Condition::Sleet => "sleet",
+            Condition::Snow => "snow",
+        }
+    }
+
+    /// Whether anything is falling from the sky.
+    pub fn is_precipitation(&self) -> bool {
+        match *self {
+            Condition::Clear | Condition::PartlyCloudy | Condition::Overcast | Condition::Fog => false,
+            _ => true,
+        }
+    }
+}
+
+impl Default for Condition {
+    fn default() -> Condition {
+        Condition::Clear
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Place {
+    pub name: String,
+    pub country: String,
+    /// How far ahead of UTC the clocks of the place are, in seconds, if the
+    /// provider tells
+    pub utc_offset_s: Option<i64>,
+}
+
+impl Place {
+    /// The local time of the place now, as `YYYY-MM-DD HH:MM`: the one of the
+    /// machine, when the UTC offset of the place is not known.
+    pub fn local_now(&self) -> String {
+        match self.utc_offset_s {
+            Some(offset) => local_time(time::get_time().sec, offset),
+            None => time::strftime("%Y-%m-%d %H:%M", &time::now()).unwrap_or(String::new()),
+        }
+    }
+}
+
+/// A provider-neutral snapshot of the weather at a given place and time.
+#[derive(Debug, Clone, Default)]
+pub struct Observation {
+    pub place: Place,
+    /// Local date and time of the observation, as `YYYY-MM-DD HH:MM`.
+    pub local_time: String,
+    /// Temperature in celsius
+    pub temp_c: f32,
+    /// Feels like temperature in celsius
+    pub feelslike_c: f32,
+    /// Wind speed in kilometer per hour
+    pub wind_kph: f32,
+    /// Wind gusts in kilometer per hour, if the provider reports them
+    pub gust_kph: Option<f32>,
+    /// Visibility in kilometers, if the provider reports it
+    pub visibility_km: Option<f32>,
+    /// Dew point in celsius, if the provider reports it
+    pub dew_point_c: Option<f32>,
+    /// Humidity as percentage, if the provider reports it
+    pub humidity: Option<u8>,
+    /// Cloud cover as percentage, if the provider reports it
+    pub cloud: Option<u8>,
+    /// Precipitation amount in millimeters (over the last or next hour,
+    /// depending on the provider), if the provider reports it
+    pub precip_mm: Option<f32>,
+    /// Probability of precipitation as percentage, if the provider forecasts it
+    pub precip_chance: Option<u8>,
+    /// UV index, if the provider reports it
+    pub uv_index: Option<f32>,
+    /// Elevation of the station (or of the forecast grid cell) in meters,
+    /// if the provider reports it
+    pub elevation_m: Option<f32>,
+    pub is_day: bool,
+    pub condition: Condition,
+    /// The provider's own description of the condition, e.g. "Light rain".
+    pub condition_text: String,
+}
+
+impl Observation {
+    pub fn temp_f(&self) -> f32 {
+        celsius_to_fahrenheit(self.temp_c)
+    }
+
+    pub fn feelslike_f(&self) -> f32 {
+        celsius_to_fahrenheit(self.feelslike_c)
+    }
+
+    pub fn wind_mph(&self) -> f32 {
+        self.wind_kph / KPH_PER_MPH
+    }
+
+    /// The relative humidity as reported, or worked out of the dew point.
+    pub fn relative_humidity(&self) -> Option<f32> {
+        self.humidity
+            .map(|h| h as f32)
+            .or(self.dew_point_c.map(|d| relative_humidity(self.temp_c, d)))
+    }
+
+    /// The dew point as reported, or worked out of the humidity.
+    pub fn dew_point(&self) -> Option<f32> {
+        self.dew_point_c.or(self.humidity.map(|h| dew_point_c(self.temp_c, h as f32)))
+    }
+}
+
+/// Weather values given by hand, replacing the ones of an observation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Overrides {
+    pub temp_c: Option<f32>,
+    pub feelslike_c: Option<f32>,
+    pub wind_kph: Option<f32>,
+    pub condition: Option<Condition>,
+    pub humidity: Option<u8>,
+    /// As `YYYY-MM-DD HH:MM`
+    pub local_time: Option<String>,
+}
+
+impl Overrides {
+    /// Replaces the overridden values of `obs`. A new temperature keeps the
+    /// gap to the feels like one, unless that's overridden too.
+    pub fn apply(&self, obs: &Observation) -> Observation {
+        let mut result = obs.clone();
+        if let Some(t) = self.temp_c {
+            result.temp_c = t;
+            result.feelslike_c = t + (obs.feelslike_c - obs.temp_c);
+            // The dew point no longer matches the temperature.
+            result.dew_point_c = None;
+        }
+        if let Some(f) = self.feelslike_c {
+            result.feelslike_c = f;
+        }
+        if let Some(w) = self.wind_kph {
+            result.wind_kph = w;
+            result.gust_kph = None;
+        }
+        if let Some(c) = self.condition {
+            result.condition = c;
+            result.condition_text = c.name().to_owned();
+            // How much it rains is up to the new condition.
+            result.precip_mm = None;
+            result.precip_chance = None;
+        }
+        if let Some(h) = self.humidity {
+            result.humidity = Some(h);
+            result.dew_point_c = None;
+        }
+        if let Some(ref t) = self.local_time {
+            result.local_time = t.clone();
+            if let Some(h) = parse_hours_from_local_time(t) {
+                result.is_day = is_daytime(h);
+            }
+        }
+        result
+    }
+
+    /// An observation made only of the overridden values, if at least the
+    /// temperature is given.
+    pub fn synthetic(&self) -> Option<Observation> {
+        self.temp_c.map(|_| {
+            let now = time::strftime("%Y-%m-%d %H:%M", &time::now()).unwrap_or(String::new());
+            let base = Observation {
+                is_day: parse_hours_from_local_time(&now).map(is_daytime).unwrap_or(true),
+                local_time: now,
+                condition_text: Condition::Clear.name().to_owned(),
+                ..Default::default()
+            };
+            self.apply(&base)
+        })
+    }
+}
+
+static KPH_PER_MPH: f32 = 1.609344;
+
+pub fn celsius_to_fahrenheit(c: f32) -> f32 {
+    c * 9.0 / 5.0 + 32.0
+}
+
+/// A rough guess for when the provider doesn't tell day from night.
+pub fn is_daytime(hour: u8) -> bool {
+    hour >= 7 && hour < 19
+}
+
+/// Relative humidity (as percentage) out of temperature and dew point, using
+/// the Magnus formula.
+pub fn relative_humidity(temp_c: f32, dew_point_c: f32) -> f32 {
+    let magnus = |t: f32| (17.625 * t / (243.04 + t)).exp();
+    (100.0 * magnus(dew_point_c) / magnus(temp_c)).min(100.0)
+}
+
+/// The dew point in celsius out of temperature and relative humidity (as
+/// percentage), using the Magnus formula.
+pub fn dew_point_c(temp_c: f32, humidity: f32) -> f32 {
+    let gamma = (humidity.max(1.0) / 100.0).ln() + 17.625 * temp_c / (243.04 + temp_c);
+    243.04 * gamma / (17.625 - gamma)
+}
+
+/// How cold the wind makes it feel, using the North American wind chill
+/// index. It only applies at or below 10°C, with some wind.
+pub fn wind_chill_c(temp_c: f32, wind_kph: f32) -> f32 {
+    if temp_c > 10.0 || wind_kph <= 4.8 {
+        return temp_c;
+    }
+    let v = wind_kph.powf(0.16);
+    (13.12 + 0.6215 * temp_c - 11.37 * v + 0.3965 * temp_c * v).min(temp_c)
+}
+
+/// How hot the humidity makes it feel, using the heat index of the US National
+/// Weather Service (the Rothfusz regression). It only applies from 80°F up.
+pub fn heat_index_c(temp_c: f32, humidity: f32) -> f32 {
+    let t = celsius_to_fahrenheit(temp_c);
+    if t < 80.0 {
+        return temp_c;
+    }
+    let r = humidity;
+    let hi = -42.379 + 2.04901523 * t + 10.14333127 * r - 0.22475541 * t * r -
+             0.00683783 * t * t - 0.05481717 * r * r + 0.00122874 * t * t * r +
+             0.00085282 * t * r * r - 0.00000199 * t * t * r * r;
+    ((hi - 32.0) * 5.0 / 9.0).max(temp_c)
+}
+
+/// The temperature it feels like: the wind chill in the cold, the heat index
+/// in the heat (if the humidity is known), the temperature otherwise.
+pub fn apparent_temperature_c(temp_c: f32, wind_kph: f32, humidity: Option<f32>) -> f32 {
+    if temp_c <= 10.0 {
+        wind_chill_c(temp_c, wind_kph)
+    } else {
+        humidity.map(|h| heat_index_c(temp_c, h)).unwrap_or(temp_c)
+    }
+}
+
+/// A source of weather observations.
+pub trait WeatherProvider {
+    /// The name used to select this provider (e.g. on the command line).
+    fn name(&self) -> &'static str;
+
+    /// Gets the current weather for the given location.
+    fn current_weather(&self, client: &Client, query: &Query) -> Result<Observation, WeatherError>;
+
+    /// Gets the forecast for the given location, one observation per slot
+    /// (usually an hour) in chronological order.
+    fn hourly_forecast(&self,
+                       _client: &Client,
+                       _query: &Query)
+                       -> Result<Vec<Observation>, WeatherError> {
+        Err(WeatherError::NoForecast(self.name()))
+    }
+}
+
+/// Minutes since the epoch of a `YYYY-MM-DD HH:MM` local time, only good
+/// to compare local times of the same place.
+pub fn local_minutes(local_time: &str) -> Option<i64> {
+    time::strptime(local_time, "%Y-%m-%d %H:%M").ok().map(|tm| tm.to_timespec().sec / 60)
+}
+
+/// The forecast slots which have a valid local time, in minutes (as per
+/// `local_minutes`), together with how long a slot lasts.
+pub fn timed_slots(forecast: &[Observation]) -> (Vec<(i64, &Observation)>, i64) {
+    let slots: Vec<(i64, &Observation)> = forecast.iter()
+        .filter_map(|o| local_minutes(&o.local_time).map(|m| (m, o)))
+        .collect();
+    // Assuming the forecast is evenly spaced.
+    let slot_length = if slots.len() >= 2 {
+        slots[slots.len() - 1].0 - slots[slots.len() - 2].0
+    } else {
+        60
+    };
+    (slots, slot_length)
+}
+
+/// Picks the forecast slot a run starting at `start` (`YYYY-MM-DD HH:MM`,
+/// local to the forecast place) falls in.
+pub fn forecast_at(forecast: &[Observation], start: &str) -> Result<Observation, WeatherError> {
+    let out_of_range = || WeatherError::ForecastOutOfRange(start.to_owned());
+    let at = try!(local_minutes(start).ok_or(out_of_range()));
+    let (slots, slot_length) = timed_slots(forecast);
+    let slot = match slots.iter().take_while(|&&(m, _)| m <= at).last() {
+        Some(&(m, o)) if at < m + slot_length => o,
+        Some(_) => return Err(out_of_range()),
+        // Before the first slot, which is usually the current hour.
+        None => {
+            match slots.first() {
+                Some(&(m, o)) if at >= m - 60 => o,
+                _ => return Err(out_of_range()),
+            }
+        }
+    };
+    let mut obs = slot.clone();
+    obs.local_time = start.to_owned();
+    Ok(obs)
+}
+
+#[derive(Debug)]
+pub enum WeatherError {
+    UnknownProvider(String),
+    /// The env var holding the API key for the provider is not set.
+    MissingApiKey(&'static str),
+    /// The provider can't infer the location by itself.
+    MissingLocation(&'static str),
+    LocationNotFound(String),
+    /// A provider setting in the configuration has an invalid value.
+    InvalidSetting(String),
+    /// The provider answered, but not with what we expected.
+    UnexpectedResponse(String),
+    /// A METAR report given by the user couldn't be decoded.
+    InvalidMetar(String),
+    /// A weather file given by the user couldn't be decoded.
+    InvalidWeatherFile(String),
+    /// The provider (or input) only knows about the current weather.
+    NoForecast(&'static str),
+    /// The planned start is not covered by the forecast.
+    ForecastOutOfRange(String),
+    FailedToContactRemoteHost(hyper::error::Error),
+    InvalidRequest(String, hyper::client::Response),
+    IOError(std::io::Error),
+    ParseJsonError(serde_json::error::Error),
+}
+
+impl From<hyper::error::Error> for WeatherError {
+    fn from(err: hyper::error::Error) -> WeatherError {
+        WeatherError::FailedToContactRemoteHost(err)
+    }
+}
+
+impl From<serde_json::error::Error> for WeatherError {
+    fn from(err: serde_json::error::Error) -> WeatherError {
+        WeatherError::ParseJsonError(err)
+    }
+}
+
+impl From<std::io::Error> for WeatherError {
+    fn from(err: std::io::Error) -> WeatherError {
+        WeatherError::IOError(err)
+    }
+}
+
+/// GETs the given url, failing on any non-200 response.
+pub fn fetch(client: &Client, url: &str) -> Result<String, WeatherError> {
+    let mut response = try!(client.get(url).send());
+    if response.status != hyper::status::StatusCode::Ok {
+        return Err(WeatherError::InvalidRequest(url.to_owned(), response));
+    }
+    let mut body = String::new();
+    let _ = try!(response.read_to_string(&mut body));
+    Ok(body)
+}
+
+/// How viktor identifies itself to the APIs which ask for it, optionally
+/// with a way to contact the user (e.g. an email).
+pub fn user_agent(contact: &Option<String>) -> String {
+    match *contact {
+        None => format!("viktor/{} {}", env!("CARGO_PKG_VERSION"), VIKTOR_HOMEPAGE),
+        Some(ref c) => format!("viktor/{} {} {}", env!("CARGO_PKG_VERSION"), VIKTOR_HOMEPAGE, c),
+    }
+}
+
+/// Builds the provider registered under `name`, configured via `settings`.
+pub fn provider(name: &str, settings: &Settings) -> Result<Box<WeatherProvider>, WeatherError> {
+    match name {
+        "apixu" => Apixu::from_env().map(|p| Box::new(p) as Box<WeatherProvider>),
+        "open-meteo" => Ok(Box::new(OpenMeteo::new())),
+        "openweathermap" => {
+            let units = try!(Units::parse(settings.openweathermap_units
+                .as_ref()
+                .map(|u| u.as_str())
+                .unwrap_or("metric")));
+            OpenWeatherMap::new(&settings.openweathermap_api_key, units)
+                .map(|p| Box::new(p) as Box<WeatherProvider>)
+        }
+        "met-norway" => Ok(Box::new(MetNorway::new(&settings.met_norway_contact))),
+        "nws" => Ok(Box::new(Nws::new(&settings.nws_contact))),
+        _ => Err(WeatherError::UnknownProvider(name.to_owned())),
+    }
+}
+
+/// Formats a UTC unix time, shifted by `utc_offset` seconds, as `YYYY-MM-DD HH:MM`.
+pub fn local_time(unix_time: i64, utc_offset: i64) -> String {
+    let tm = time::at_utc(time::Timespec::new(unix_time + utc_offset, 0));
+    time::strftime("%Y-%m-%d %H:%M", &tm).unwrap_or(String::new())
+}
+
+/// The UTC offset at the given longitude, in seconds, for the providers
+/// which only report UTC times. It's the nautical time zone (an hour every
+/// 15 degrees), which knows nothing of daylight saving time or borders, so
+/// it can be an hour or so off the clocks of the place.
+pub fn nautical_utc_offset(longitude: f32) -> i64 {
+    (longitude / 15.0).round() as i64 * 3600
+}
+
+pub fn parse_hours_from_local_time<'a>(local_time: &'a str) -> Option<u8> {
+    match *local_time.to_owned().split_whitespace().collect::<Vec<_>>().as_slice() {
+        [_, time] => {
+            match *time.split(":").collect::<Vec<_>>().as_slice() {
+                [h, _] => return str::parse(h).ok(),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::Settings;
+
+    #[test]
+    fn can_parse_local_time_into_hours() {
+        let test1 = "2016-12-15 09:22";
+        let test2 = "2016-12-15 3:24";
+        assert_eq!(parse_hours_from_local_time(&test1), Some(9));
+        assert_eq!(parse_hours_from_local_time(&test2), Some(3));
+    }
+
+    #[test]
+    fn computes_the_wind_chill_and_the_heat_index() {
+        let chill = wind_chill_c(-10.0, 30.0);
+        assert!(chill > -20.0 && chill < -19.0, "wind chill: {}", chill);
+        assert_eq!(wind_chill_c(-10.0, 3.0), -10.0);
+        assert_eq!(wind_chill_c(15.0, 30.0), 15.0);
+
+        let heat = heat_index_c(32.0, 70.0);
+        assert!(heat > 40.0 && heat < 41.5, "heat index: {}", heat);
+        assert_eq!(heat_index_c(20.0, 90.0), 20.0);
+
+        assert_eq!(apparent_temperature_c(18.0, 40.0, Some(90.0)), 18.0);
+        assert_eq!(apparent_temperature_c(30.0, 10.0, None), 30.0);
+    }
+
+    #[test]
+    fn can_parse_condition_names() {
+        for name in CONDITIONS.iter() {
+            let condition = Condition::parse(name).expect("Not a condition");
+            assert_eq!(condition.name(), *name);
+        }
+        assert_eq!(Condition::parse("light-rain"), Some(Condition::LightRain));
+        assert_eq!(Condition::parse("sunny"), None);
+    }
+
+    #[test]
+    fn overrides_replace_only_what_is_given() {
+        let fetched = Observation {
+            local_time: "2026-10-18 12:30".to_owned(),
+            temp_c: 18.0,
+            feelslike_c: 16.0,
+            wind_kph: 12.0,
+            humidity: Some(68),
+            is_day: true,
+            condition: Condition::LightRain,
+            ..Default::default()
+        };
+        let overrides = Overrides {
+            temp_c: Some(5.0),
+            condition: Some(Condition::Snow),
+            local_time: Some("2026-10-18 05:30".to_owned()),
+            ..Default::default()
+        };
+        let obs = overrides.apply(&fetched);
+        assert_eq!(obs.temp_c, 5.0);
+        assert_eq!(obs.feelslike_c, 3.0);
+        assert_eq!(obs.wind_kph, 12.0);
+        assert_eq!(obs.humidity, Some(68));
+        assert_eq!(obs.condition, Condition::Snow);
+        assert_eq!(obs.condition_text, "snow");
+        assert!(!obs.is_day);
+    }
+
+    #[test]
+    fn synthetic_observations_need_a_temperature() {
+        assert!(Overrides::default().synthetic().is_none());
+        let overrides = Overrides { temp_c: Some(10.0), ..Default::default() };
+        let obs = overrides.synthetic().unwrap();
+        assert_eq!(obs.temp_c, 10.0);
+        assert_eq!(obs.feelslike_c, 10.0);
+        assert_eq!(obs.condition, Condition::Clear);
+    }
+
+    fn slot(local_time: &str, temp_c: f32) -> Observation {
+        Observation { local_time: local_time.to_owned(), temp_c: temp_c, ..Default::default() }
+    }
+
+    #[test]
+    fn picks_the_forecast_slot_of_the_planned_start() {
+        let forecast = vec![slot("2026-10-19 05:00", 4.0),
+                            slot("2026-10-19 06:00", 5.0),
+                            slot("2026-10-19 07:00", 7.0)];
+        let obs = forecast_at(&forecast, "2026-10-19 06:30").unwrap();
+        assert_eq!(obs.temp_c, 5.0);
+        assert_eq!(obs.local_time, "2026-10-19 06:30");
+        assert_eq!(forecast_at(&forecast, "2026-10-19 07:59").unwrap().temp_c, 7.0);
+        assert_eq!(forecast_at(&forecast, "2026-10-19 04:30").unwrap().temp_c, 4.0);
+        assert!(forecast_at(&forecast, "2026-10-19 08:00").is_err());
+        assert!(forecast_at(&forecast, "2026-10-18 06:30").is_err());
+    }
+
+    #[test]
+    fn unknown_providers_are_rejected() {
+        match provider("weather-rock", &Settings::default()) {
+            Err(WeatherError::UnknownProvider(p)) => assert_eq!(p, "weather-rock"),
+            _ => panic!("Expected an UnknownProvider error"),
+        }
+    }
+}